@@ -4,6 +4,7 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use kli::colors::{BORDER_COLORS, SPECIAL_COLORS};
 use rand::Rng;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
@@ -27,12 +28,6 @@ struct SpecialObject {
     color: Color,
 }
 
-const SPECIAL_COLORS: [Color; 3] = [
-    Color::Rgb(128, 0, 128),
-    Color::Cyan,
-    Color::Rgb(255, 165, 0),
-];
-
 #[derive(Clone, Copy, PartialEq)]
 enum Direction {
     None,
@@ -374,9 +369,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                     });
                 }
                 // swap fst_colors
-                let temp = app.fst_color;
-                app.fst_color = app.snd_color;
-                app.snd_color = temp;
+                std::mem::swap(&mut app.fst_color, &mut app.snd_color);
                 // if both have same fst_color now, uncolor
                 if app.fst_color == app.snd_color && app.fst_color.is_some() {
                     app.fst_color = None;
@@ -400,10 +393,10 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
 
 fn new_color_by_border(dir: Direction) -> Option<Color> {
     match dir {
-        Direction::Left => Some(Color::Red),
-        Direction::Right => Some(Color::Blue),
-        Direction::Up => Some(Color::Green),
-        Direction::Down => Some(Color::Yellow),
+        Direction::Left => Some(BORDER_COLORS[0]),
+        Direction::Right => Some(BORDER_COLORS[1]),
+        Direction::Up => Some(BORDER_COLORS[2]),
+        Direction::Down => Some(BORDER_COLORS[3]),
         _ => None,
     }
 }
@@ -0,0 +1,151 @@
+//! Periodic copies of unsaved drawings in the user's state directory, so a closed terminal or a
+//! killed process doesn't lose them.
+//!
+//! Files are written by a background thread so a slow disk never holds up the editor.
+
+use crate::{
+    file,
+    grid::{Cells, Grid},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How often unsaved changes are written.
+pub const INTERVAL: Duration = Duration::from_secs(10);
+
+enum Job {
+    Write(PathBuf, Vec<u8>),
+    Remove(PathBuf),
+}
+
+pub struct Autosave {
+    path: PathBuf,
+    /// Frames as they are in the drawing file, nothing needs recovering while they are on screen.
+    saved: Vec<Cells>,
+    /// Frames in the autosave file.
+    autosaved: Option<Vec<Cells>>,
+    last: Instant,
+    jobs: Sender<Job>,
+    writer: JoinHandle<()>,
+}
+
+impl Autosave {
+    /// Autosaves of the drawing at `drawing`, `None` when the platform has no place for them.
+    pub fn new(drawing: &Path, grid: &Grid) -> Option<Self> {
+        let dir = dirs::state_dir()
+            .or_else(dirs::data_local_dir)?
+            .join("kli")
+            .join("pp");
+        let path = dir.join(file_name(drawing));
+        let (jobs, receiver) = mpsc::channel();
+        let writer = thread::spawn(move || {
+            for job in receiver {
+                // there is nobody to tell about failures, the next autosave tries again
+                let _ = match job {
+                    Job::Write(path, data) => write(&path, &data),
+                    Job::Remove(path) => fs::remove_file(path),
+                };
+            }
+        });
+        Some(Autosave {
+            path,
+            saved: grid.frames.clone(),
+            autosaved: None,
+            last: Instant::now(),
+            jobs,
+            writer,
+        })
+    }
+
+    /// The drawing left behind by an earlier session, if it differs from the saved one; other
+    /// leftovers are removed.
+    pub fn recover(&mut self) -> Option<Grid> {
+        match file::load(&self.path) {
+            Ok(grid) if grid.frames != self.saved => Some(grid),
+            _ => {
+                self.discard();
+                None
+            }
+        }
+    }
+
+    /// Time until the next autosave is due.
+    pub fn time_left(&self) -> Duration {
+        INTERVAL.saturating_sub(self.last.elapsed())
+    }
+
+    /// Writes the drawing if it changed since the last autosave and is due, or right away when
+    /// `now` is set.
+    pub fn tick(&mut self, grid: &Grid, now: bool) {
+        if !now && self.time_left() > Duration::ZERO {
+            return;
+        }
+        self.last = Instant::now();
+        if grid.frames == self.saved {
+            if self.autosaved.take().is_some() {
+                let _ = self.jobs.send(Job::Remove(self.path.clone()));
+            }
+        } else if self.autosaved.as_ref() != Some(&grid.frames) {
+            self.autosaved = Some(grid.frames.clone());
+            let _ = self
+                .jobs
+                .send(Job::Write(self.path.clone(), file::encode(grid)));
+        }
+    }
+
+    /// Records that `grid` is what the drawing file now holds.
+    pub fn saved(&mut self, grid: &Grid) {
+        self.saved = grid.frames.clone();
+        self.discard();
+    }
+
+    /// Removes the autosave file.
+    pub fn discard(&mut self) {
+        self.autosaved = None;
+        let _ = self.jobs.send(Job::Remove(self.path.clone()));
+    }
+
+    /// Follows the drawing to a new file name, autosaving under it from the next tick on.
+    pub fn rename(&mut self, drawing: &Path) {
+        self.discard();
+        self.path = self.path.with_file_name(file_name(drawing));
+    }
+
+    /// Writes pending changes and waits for the writer to finish.
+    pub fn finish(mut self, grid: &Grid) {
+        self.tick(grid, true);
+        drop(self.jobs);
+        let _ = self.writer.join();
+    }
+}
+
+/// Writes through a temporary file so a crash mid-write doesn't destroy the previous autosave.
+fn write(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, data)?;
+    fs::rename(temp, path)
+}
+
+/// A file name unique to the drawing's absolute path: its name followed by a hash of the path.
+fn file_name(drawing: &Path) -> String {
+    let absolute = std::path::absolute(drawing).unwrap_or_else(|_| drawing.to_path_buf());
+    // FNV-1a, stable across Rust versions unlike the standard library's hasher
+    let hash = absolute
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+    let stem = drawing
+        .file_stem()
+        .map_or("drawing".into(), |stem| stem.to_string_lossy());
+    format!("{}-{:016x}.kli", stem, hash)
+}
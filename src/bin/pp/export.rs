@@ -0,0 +1,196 @@
+use crate::{
+    grid::{Cells, Grid},
+    palette::{self, EMPTY},
+};
+use ratatui::style::Color;
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+/// Most pixels an exported image may have, about 200 MB of RGB data.
+const MAX_PIXELS: u64 = 1 << 26;
+
+pub struct Options {
+    /// Size in pixels of one cell.
+    pub scale: usize,
+    /// Delay between animation frames in milliseconds, the drawing's own speed when unset.
+    pub delay_ms: Option<u32>,
+    /// How many times animations play, 0 loops forever.
+    pub loops: u16,
+    /// Characters of filled and empty cells in plain text exports.
+    pub on: char,
+    pub off: char,
+}
+
+/// Writes the drawing as an animated GIF, ANSI colored text or plain text when `path` ends in
+/// `.gif`, `.ans` or `.txt`, and as a PNG otherwise; all but GIFs show the current frame.
+pub fn write(grid: &Grid, path: &Path, options: &Options) -> io::Result<()> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("gif") => write_gif(grid, path, options),
+        Some("ans") => fs::write(path, ansi(grid.cells())),
+        Some("txt") => fs::write(path, plain(grid.cells(), options.on, options.off)),
+        _ => write_png(grid, path, options.scale),
+    }
+}
+
+/// The cells as colored text to print in a terminal, two rows per line with half blocks so
+/// cells stay square.
+pub fn ansi(cells: &Cells) -> String {
+    let mut out = String::new();
+    for pair in cells.chunks(2) {
+        for (x, &top) in pair[0].iter().enumerate() {
+            let bottom = pair.get(1).map_or(EMPTY, |row| row[x]);
+            match (palette::color(top), palette::color(bottom)) {
+                (None, None) => out.push(' '),
+                (Some(top), None) => out += &format!("\x1b[{}m▀\x1b[0m", sgr(top, false)),
+                (None, Some(bottom)) => out += &format!("\x1b[{}m▄\x1b[0m", sgr(bottom, false)),
+                (Some(top), Some(bottom)) => {
+                    out += &format!("\x1b[{};{}m▀\x1b[0m", sgr(top, false), sgr(bottom, true))
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The SGR parameters selecting `color` as foreground or background.
+fn sgr(color: Color, background: bool) -> String {
+    let base = if background { 40 } else { 30 };
+    match color {
+        Color::Black => base.to_string(),
+        Color::Red => (base + 1).to_string(),
+        Color::Green => (base + 2).to_string(),
+        Color::Yellow => (base + 3).to_string(),
+        Color::Blue => (base + 4).to_string(),
+        Color::Magenta => (base + 5).to_string(),
+        Color::Cyan => (base + 6).to_string(),
+        Color::Gray => (base + 7).to_string(),
+        Color::White => (base + 60 + 7).to_string(),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+        _ => (base + 9).to_string(),
+    }
+}
+
+/// The cells as plain text, one character per cell.
+fn plain(cells: &Cells, on: char, off: char) -> String {
+    let mut out = String::new();
+    for row in cells {
+        out.extend(row.iter().map(|&c| if c == EMPTY { off } else { on }));
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes the current frame as a PNG where every cell becomes a `scale`x`scale` square.
+fn write_png(grid: &Grid, path: &Path, scale: usize) -> io::Result<()> {
+    let scale = scale.max(1);
+    let (width, height) = size(grid, scale)?;
+    let data = scale_up(grid.cells(), scale, |cell| palette::rgb(cell).to_vec());
+
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes every frame into an animated GIF, a still drawing becomes a single-frame GIF.
+fn write_gif(grid: &Grid, path: &Path, options: &Options) -> io::Result<()> {
+    let scale = options.scale.max(1);
+    let (width, height) = size(grid, scale)?;
+    let width = u16::try_from(width).map_err(|_| too_big())?;
+    let height = u16::try_from(height).map_err(|_| too_big())?;
+    // the color table is indexed by cell value, so cells are already pixel indices
+    let color_table: Vec<u8> = (0..=palette::PALETTE.len() as u8)
+        .flat_map(palette::rgb)
+        .collect();
+    let delay_ms = options.delay_ms.unwrap_or(1000 / grid.fps);
+
+    let file = File::create(path)?;
+    let mut encoder = gif::Encoder::new(io::BufWriter::new(file), width, height, &color_table)
+        .map_err(io::Error::other)?;
+    let repeat = match options.loops {
+        0 => gif::Repeat::Infinite,
+        n => gif::Repeat::Finite(n - 1),
+    };
+    encoder.set_repeat(repeat).map_err(io::Error::other)?;
+    for cells in &grid.frames {
+        let frame = gif::Frame {
+            width,
+            height,
+            // GIF delays are in hundredths of a second
+            delay: u16::try_from(delay_ms / 10).unwrap_or(u16::MAX),
+            buffer: Cow::Owned(scale_up(cells, scale, |cell| vec![cell])),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// Row-major pixel data with every cell repeated into a `scale`x`scale` square.
+fn scale_up(cells: &Cells, scale: usize, pixel: impl Fn(u8) -> Vec<u8>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for row in cells {
+        let mut line = Vec::new();
+        for &cell in row {
+            let value = pixel(cell);
+            for _ in 0..scale {
+                line.extend_from_slice(&value);
+            }
+        }
+        for _ in 0..scale {
+            data.extend_from_slice(&line);
+        }
+    }
+    data
+}
+
+/// Width and height in pixels of the image of `grid`, checked before any pixel is made.
+fn size(grid: &Grid, scale: usize) -> io::Result<(u32, u32)> {
+    let pixels = |cells: usize| {
+        cells
+            .checked_mul(scale)
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(too_big)
+    };
+    let (width, height) = (pixels(grid.width)?, pixels(grid.height)?);
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(too_big());
+    }
+    Ok((width, height))
+}
+
+fn too_big() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "image is too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_huge_images_before_drawing_them() {
+        let grid = Grid::new(30, 20);
+        assert!(size(&grid, 16).is_ok());
+        let err = size(&grid, 100_000).unwrap_err();
+        assert_eq!(err.to_string(), "image is too large");
+        assert!(size(&grid, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn plain_text_marks_filled_cells() {
+        let cells = vec![vec![1, EMPTY], vec![EMPTY, 9]];
+        assert_eq!(plain(&cells, '#', '.'), "#.\n.#\n");
+    }
+}
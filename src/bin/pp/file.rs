@@ -0,0 +1,261 @@
+//! The `.kli` drawing format.
+//!
+//! A plain-text file so drawings can be inspected and even edited by hand:
+//!
+//! ```text
+//! kli-pp 3
+//! 8 3
+//! 2 1
+//! 2 8
+//! ........
+//! .12..34.
+//! ........
+//!
+//! ........
+//! ........
+//! .12..34.
+//! ```
+//!
+//! The first line holds the magic and the format version, then come the grid
+//! width and height, the cursor position, the number of frames with the
+//! playback speed in frames per second and the frames themselves, one line per
+//! row of cells and an empty line between frames.
+//! Cells are `.` when empty or the digit of their palette color.
+//!
+//! Version 2 files have no frames line and a single frame; version 1 files
+//! also only had `#` for filled cells, which are loaded as black.
+
+use crate::{
+    grid::{Grid, DEFAULT_FPS, MAX_FPS},
+    palette::{EMPTY, PALETTE},
+};
+use std::{fs, io, path::Path};
+
+const MAGIC: &str = "kli-pp";
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Color given to the filled cells of version 1 files.
+const V1_FILLED: u8 = PALETTE.len() as u8;
+
+pub fn save(grid: &Grid, path: &Path) -> io::Result<()> {
+    fs::write(path, encode(grid))
+}
+
+/// The file contents for `grid`.
+pub fn encode(grid: &Grid) -> Vec<u8> {
+    let mut out = String::new();
+    out += &format!("{} {}\n", MAGIC, FORMAT_VERSION);
+    out += &format!("{} {}\n", grid.width, grid.height);
+    out += &format!("{} {}\n", grid.cursor_x, grid.cursor_y);
+    out += &format!("{} {}\n", grid.frames.len(), grid.fps);
+    for (i, frame) in grid.frames.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        for row in frame {
+            out.extend(row.iter().map(|&c| match c {
+                EMPTY => '.',
+                _ => char::from(b'0' + c),
+            }));
+            out.push('\n');
+        }
+    }
+    out.into_bytes()
+}
+
+pub fn load(path: &Path) -> io::Result<Grid> {
+    let content = fs::read_to_string(path)?;
+    parse(&content)
+}
+
+/// The drawing in file contents made by [`encode`].
+pub fn decode(data: &[u8]) -> io::Result<Grid> {
+    let content = std::str::from_utf8(data).map_err(|err| invalid(err.to_string()))?;
+    parse(content)
+}
+
+fn parse(content: &str) -> io::Result<Grid> {
+    let mut lines = content.lines();
+
+    let header = lines.next().unwrap_or_default();
+    let version = match header.split_once(' ') {
+        Some((MAGIC, version)) => version
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| invalid(format!("bad format version {:?}", version)))?,
+        _ => return Err(invalid("not a pp drawing".to_string())),
+    };
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(invalid(format!(
+            "format version {} is not supported (this pp reads up to version {})",
+            version, FORMAT_VERSION
+        )));
+    }
+
+    let (width, height) = parse_pair(lines.next(), "size")?;
+    if width == 0 || height == 0 {
+        return Err(invalid(format!("empty drawing size {}x{}", width, height)));
+    }
+    let (cursor_x, cursor_y) = parse_pair(lines.next(), "cursor")?;
+    if cursor_x >= width || cursor_y >= height {
+        return Err(invalid(format!(
+            "cursor {},{} is outside the {}x{} drawing",
+            cursor_x, cursor_y, width, height
+        )));
+    }
+
+    let (frame_count, fps) = if version >= 3 {
+        let (frame_count, fps) = parse_pair(lines.next(), "frames")?;
+        if frame_count == 0 || fps == 0 || fps > MAX_FPS as usize {
+            return Err(invalid(format!(
+                "bad frames line: {} frames at {} fps",
+                frame_count, fps
+            )));
+        }
+        (frame_count, fps as u32)
+    } else {
+        (1, DEFAULT_FPS)
+    };
+
+    // the size comes from the file, so it is checked against the rows that are really there
+    // before anything that large is allocated
+    let needed = height
+        .checked_mul(frame_count)
+        .and_then(|rows| rows.checked_add(frame_count - 1));
+    if needed.is_none_or(|needed| needed > lines.clone().count()) {
+        return Err(invalid(format!(
+            "too few rows for {} frames of {}x{} cells",
+            frame_count, width, height
+        )));
+    }
+
+    let mut frames = Vec::new();
+    for f in 0..frame_count {
+        if f > 0 && lines.next().is_some_and(|line| !line.is_empty()) {
+            return Err(invalid(format!(
+                "missing empty line before frame {}",
+                f + 1
+            )));
+        }
+        let mut frame = Vec::new();
+        for y in 0..height {
+            let line = lines.next().ok_or_else(|| {
+                invalid(format!(
+                    "frame {} is missing row {} of {}",
+                    f + 1,
+                    y + 1,
+                    height
+                ))
+            })?;
+            frame.push(
+                parse_row(line, version, width)
+                    .map_err(|msg| invalid(format!("frame {} row {} {}", f + 1, y + 1, msg)))?,
+            );
+        }
+        frames.push(frame);
+    }
+
+    let mut grid = Grid::new(width, height);
+    grid.cursor_x = cursor_x;
+    grid.cursor_y = cursor_y;
+    grid.fps = fps;
+    grid.frames = frames;
+    Ok(grid)
+}
+
+fn parse_row(line: &str, version: u32, width: usize) -> Result<Vec<u8>, String> {
+    if line.chars().count() != width {
+        return Err(format!(
+            "has {} cells, expected {}",
+            line.chars().count(),
+            width
+        ));
+    }
+    line.chars()
+        .map(|ch| match (version, ch) {
+            (_, '.') => Ok(EMPTY),
+            (1, '#') => Ok(V1_FILLED),
+            (2.., '1'..='9') => Ok(ch as u8 - b'0'),
+            _ => Err(format!("has unknown cell {:?}", ch)),
+        })
+        .collect()
+}
+
+fn parse_pair(line: Option<&str>, what: &str) -> io::Result<(usize, usize)> {
+    let line = line.ok_or_else(|| invalid(format!("missing {} line", what)))?;
+    let mut parts = line.split_whitespace().map(str::parse::<usize>);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(a)), Some(Ok(b)), None) => Ok((a, b)),
+        _ => Err(invalid(format!("bad {} line {:?}", what, line))),
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut grid = Grid::new(4, 2);
+        grid.frames[0][1] = vec![1, EMPTY, 9, 4];
+        grid.frames.push(vec![vec![5; 4], vec![EMPTY; 4]]);
+        grid.fps = 12;
+        grid.cursor_x = 3;
+        grid.cursor_y = 1;
+        let loaded = decode(&encode(&grid)).unwrap();
+        assert_eq!(loaded.frames, grid.frames);
+        assert_eq!((loaded.width, loaded.height), (4, 2));
+        assert_eq!((loaded.cursor_x, loaded.cursor_y), (3, 1));
+        assert_eq!(loaded.fps, 12);
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let grid = parse("kli-pp 1\n3 2\n0 1\n#..\n.##\n").unwrap();
+        assert_eq!(
+            grid.frames,
+            vec![vec![
+                vec![V1_FILLED, EMPTY, EMPTY],
+                vec![EMPTY, V1_FILLED, V1_FILLED]
+            ]]
+        );
+        assert_eq!(grid.cursor_y, 1);
+        // digits came with version 2
+        assert!(parse("kli-pp 1\n1 1\n0 0\n1\n").is_err());
+    }
+
+    #[test]
+    fn reads_version_2() {
+        let grid = parse("kli-pp 2\n3 1\n2 0\n1.9\n").unwrap();
+        assert_eq!(grid.frames, vec![vec![vec![1, EMPTY, 9]]]);
+        assert_eq!(grid.fps, DEFAULT_FPS);
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let err = parse("kli-pp 4\n1 1\n0 0\n1 8\n.\n").err().unwrap();
+        assert!(err.to_string().contains("version 4"));
+    }
+
+    #[test]
+    fn refuses_bad_dimensions() {
+        assert!(parse("kli-pp 3\n0 3\n0 0\n1 8\n").is_err());
+        assert!(parse("kli-pp 3\n2 2\n2 0\n1 8\n..\n..\n").is_err());
+        // huge sizes fail on the missing rows instead of allocating them
+        assert!(parse("kli-pp 3\n4000000000 4000000000\n0 0\n1 8\n").is_err());
+        let huge = format!("kli-pp 3\n1 {}\n0 0\n{} 8\n", usize::MAX, usize::MAX);
+        assert!(parse(&huge).is_err());
+        assert!(parse("kli-pp 3\n3 1\n0 0\n1 8\n....\n").is_err());
+    }
+
+    #[test]
+    fn refuses_truncated_frames() {
+        assert!(parse("kli-pp 3\n2 2\n0 0\n2 8\n..\n..\n\n..\n").is_err());
+        assert!(parse("kli-pp 3\n2 1\n0 0\n2 8\n..\n..\n").is_err());
+        assert!(parse("kli-pp 3\n2 1\n0 0\n2 8\n..\n\n..\n").is_ok());
+    }
+}
@@ -0,0 +1,137 @@
+//! An embedded 5x7 pixel font for the printable ASCII characters.
+
+use crate::{grid::Cells, palette::EMPTY};
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+/// Empty columns between two characters.
+const SPACING: usize = 1;
+
+/// Every glyph as rows of `#` for set pixels and `.` for empty ones.
+#[rustfmt::skip]
+const GLYPHS: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    (' ', [".....", ".....", ".....", ".....", ".....", ".....", "....."]),
+    ('!', ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#.."]),
+    ('"', [".#.#.", ".#.#.", ".#.#.", ".....", ".....", ".....", "....."]),
+    ('#', [".#.#.", ".#.#.", "#####", ".#.#.", "#####", ".#.#.", ".#.#."]),
+    ('$', ["..#..", ".####", "#.#..", ".###.", "..#.#", "####.", "..#.."]),
+    ('%', ["##...", "##..#", "...#.", "..#..", ".#...", "#..##", "...##"]),
+    ('&', [".##..", "#..#.", "#.#..", ".#...", "#.#.#", "#..#.", ".##.#"]),
+    ('\'', ["..#..", "..#..", ".#...", ".....", ".....", ".....", "....."]),
+    ('(', ["...#.", "..#..", ".#...", ".#...", ".#...", "..#..", "...#."]),
+    (')', [".#...", "..#..", "...#.", "...#.", "...#.", "..#..", ".#..."]),
+    ('*', [".....", "..#..", "#.#.#", ".###.", "#.#.#", "..#..", "....."]),
+    ('+', [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."]),
+    (',', [".....", ".....", ".....", ".....", ".##..", "..#..", ".#..."]),
+    ('-', [".....", ".....", ".....", "#####", ".....", ".....", "....."]),
+    ('.', [".....", ".....", ".....", ".....", ".....", ".##..", ".##.."]),
+    ('/', [".....", "....#", "...#.", "..#..", ".#...", "#....", "....."]),
+    ('0', [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."]),
+    ('1', ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('2', [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"]),
+    ('3', ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."]),
+    ('4', ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."]),
+    ('5', ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."]),
+    ('6', ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."]),
+    ('7', ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."]),
+    ('8', [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."]),
+    ('9', [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."]),
+    (':', [".....", ".##..", ".##..", ".....", ".##..", ".##..", "....."]),
+    (';', [".....", ".##..", ".##..", ".....", ".##..", "..#..", ".#..."]),
+    ('<', ["...#.", "..#..", ".#...", "#....", ".#...", "..#..", "...#."]),
+    ('=', [".....", ".....", "#####", ".....", "#####", ".....", "....."]),
+    ('>', [".#...", "..#..", "...#.", "....#", "...#.", "..#..", ".#..."]),
+    ('?', [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."]),
+    ('@', [".###.", "#...#", "....#", ".##.#", "#.#.#", "#.#.#", ".###."]),
+    ('A', [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('B', ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."]),
+    ('C', [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."]),
+    ('D', ["###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###.."]),
+    ('E', ["#####", "#....", "#....", "####.", "#....", "#....", "#####"]),
+    ('F', ["#####", "#....", "#....", "####.", "#....", "#....", "#...."]),
+    ('G', [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"]),
+    ('H', ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "#....", "#####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"]),
+    ('N', ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"]),
+    ('O', [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('P', ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."]),
+    ('Q', [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"]),
+    ('R', ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"]),
+    ('S', [".####", "#....", "#....", ".###.", "....#", "....#", "####."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('V', ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."]),
+    ('X', ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."]),
+    ('Z', ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"]),
+    ('[', [".###.", ".#...", ".#...", ".#...", ".#...", ".#...", ".###."]),
+    ('\\', [".....", "#....", ".#...", "..#..", "...#.", "....#", "....."]),
+    (']', [".###.", "...#.", "...#.", "...#.", "...#.", "...#.", ".###."]),
+    ('^', ["..#..", ".#.#.", "#...#", ".....", ".....", ".....", "....."]),
+    ('_', [".....", ".....", ".....", ".....", ".....", ".....", "#####"]),
+    ('`', [".#...", "..#..", "...#.", ".....", ".....", ".....", "....."]),
+    ('a', [".....", ".....", ".###.", "....#", ".####", "#...#", ".####"]),
+    ('b', ["#....", "#....", "#.##.", "##..#", "#...#", "#...#", "####."]),
+    ('c', [".....", ".....", ".###.", "#....", "#....", "#...#", ".###."]),
+    ('d', ["....#", "....#", ".##.#", "#..##", "#...#", "#...#", ".####"]),
+    ('e', [".....", ".....", ".###.", "#...#", "#####", "#....", ".###."]),
+    ('f', ["..##.", ".#..#", ".#...", "###..", ".#...", ".#...", ".#..."]),
+    ('g', [".....", ".####", "#...#", "#...#", ".####", "....#", ".###."]),
+    ('h', ["#....", "#....", "#.##.", "##..#", "#...#", "#...#", "#...#"]),
+    ('i', ["..#..", ".....", ".##..", "..#..", "..#..", "..#..", ".###."]),
+    ('j', ["...#.", ".....", "..##.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('k', ["#....", "#....", "#..#.", "#.#..", "##...", "#.#..", "#..#."]),
+    ('l', [".##..", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('m', [".....", ".....", "##.#.", "#.#.#", "#.#.#", "#...#", "#...#"]),
+    ('n', [".....", ".....", "#.##.", "##..#", "#...#", "#...#", "#...#"]),
+    ('o', [".....", ".....", ".###.", "#...#", "#...#", "#...#", ".###."]),
+    ('p', [".....", ".....", "####.", "#...#", "####.", "#....", "#...."]),
+    ('q', [".....", ".....", ".##.#", "#..##", ".####", "....#", "....#"]),
+    ('r', [".....", ".....", "#.##.", "##..#", "#....", "#....", "#...."]),
+    ('s', [".....", ".....", ".###.", "#....", ".###.", "....#", "####."]),
+    ('t', [".#...", ".#...", "###..", ".#...", ".#...", ".#..#", "..##."]),
+    ('u', [".....", ".....", "#...#", "#...#", "#...#", "#..##", ".##.#"]),
+    ('v', [".....", ".....", "#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('w', [".....", ".....", "#...#", "#...#", "#.#.#", "#.#.#", ".#.#."]),
+    ('x', [".....", ".....", "#...#", ".#.#.", "..#..", ".#.#.", "#...#"]),
+    ('y', [".....", ".....", "#...#", "#...#", ".####", "....#", ".###."]),
+    ('z', [".....", ".....", "#####", "...#.", "..#..", ".#...", "#####"]),
+    ('{', ["...#.", "..#..", "..#..", ".#...", "..#..", "..#..", "...#."]),
+    ('|', ["..#..", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('}', [".#...", "..#..", "..#..", "...#.", "..#..", "..#..", ".#..."]),
+    ('~', [".....", ".....", ".#...", "#.#.#", "...#.", ".....", "....."]),
+];
+
+fn glyph(ch: char) -> &'static [&'static str; GLYPH_HEIGHT] {
+    GLYPHS
+        .iter()
+        .find(|(c, _)| *c == ch)
+        .or_else(|| GLYPHS.iter().find(|(c, _)| *c == '?'))
+        .map(|(_, rows)| rows)
+        .expect("the font has a glyph for '?'")
+}
+
+/// Rasterizes one line of text in `color`, every font pixel becoming a `scale`x`scale` block;
+/// characters missing from the font are drawn as `?`.
+pub fn render(text: &str, color: u8, scale: usize) -> Cells {
+    let count = text.chars().count();
+    let width = (count * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING) * scale;
+    let mut cells = vec![vec![EMPTY; width]; GLYPH_HEIGHT * scale];
+    for (i, ch) in text.chars().enumerate() {
+        let left = i * (GLYPH_WIDTH + SPACING) * scale;
+        for (gy, row) in glyph(ch).iter().enumerate() {
+            for (gx, _) in row.bytes().enumerate().filter(|&(_, b)| b == b'#') {
+                let x = left + gx * scale;
+                for row in &mut cells[gy * scale..(gy + 1) * scale] {
+                    row[x..x + scale].fill(color);
+                }
+            }
+        }
+    }
+    cells
+}
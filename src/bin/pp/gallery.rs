@@ -0,0 +1,170 @@
+//! The user's drawings kept together in one directory, browsed as thumbnails instead of paths.
+
+use crate::{file, grid::Grid};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// A question the gallery waits on before touching a file.
+pub enum Prompt {
+    /// New name being typed for the selected drawing.
+    Rename(String),
+    /// Whether the selected drawing should really be deleted.
+    Delete,
+}
+
+pub struct Drawing {
+    pub path: PathBuf,
+    pub name: String,
+    /// The drawing, or why it couldn't be read.
+    pub grid: Result<Grid, String>,
+}
+
+pub struct Gallery {
+    dir: PathBuf,
+    pub drawings: Vec<Drawing>,
+    pub selected: usize,
+    pub prompt: Option<Prompt>,
+    /// Tiles per row as last drawn, for moving up and down.
+    pub columns: usize,
+    /// When the gallery opened, animated thumbnails play from then.
+    pub opened: Instant,
+}
+
+/// Where drawings are kept: a `pp` directory among the user's pictures, or in their home
+/// directory when there is no pictures directory.
+pub fn dir() -> Option<PathBuf> {
+    Some(dirs::picture_dir().or_else(dirs::home_dir)?.join("pp"))
+}
+
+/// A file for a new drawing in the gallery, the first free `drawing-N.kli`.
+pub fn new_drawing_path(dir: &Path) -> PathBuf {
+    (1..)
+        .map(|n| dir.join(format!("drawing-{}.kli", n)))
+        .find(|path| !path.exists())
+        .expect("some name is free")
+}
+
+impl Gallery {
+    /// The drawings in `dir`, with `current` selected if it is one of them.
+    pub fn open(dir: PathBuf, current: &Path) -> Self {
+        let mut gallery = Gallery {
+            dir,
+            drawings: Vec::new(),
+            selected: 0,
+            prompt: None,
+            columns: 1,
+            opened: Instant::now(),
+        };
+        gallery.reload();
+        gallery.select_path(Some(current));
+        gallery
+    }
+
+    /// Whether some drawing has frames to play.
+    pub fn is_animated(&self) -> bool {
+        self.drawings.iter().any(|drawing| {
+            drawing
+                .grid
+                .as_ref()
+                .is_ok_and(|grid| grid.frames.len() > 1)
+        })
+    }
+
+    /// Reads the drawings again, sorted by name, keeping the selection on the same file if it
+    /// is still there.
+    pub fn reload(&mut self) {
+        let selected = self.current().map(|drawing| drawing.path.clone());
+        let mut paths: Vec<_> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|file| Some(file.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "kli"))
+            .collect();
+        paths.sort();
+        self.drawings = paths
+            .into_iter()
+            .map(|path| Drawing {
+                name: name_of(&path),
+                grid: file::load(&path).map_err(|err| err.to_string()),
+                path,
+            })
+            .collect();
+        self.select_path(selected.as_deref());
+    }
+
+    fn select_path(&mut self, path: Option<&Path>) {
+        let found = self
+            .drawings
+            .iter()
+            .position(|drawing| Some(drawing.path.as_path()) == path);
+        self.selected = found.unwrap_or(self.selected.min(self.drawings.len().saturating_sub(1)));
+    }
+
+    pub fn current(&self) -> Option<&Drawing> {
+        self.drawings.get(self.selected)
+    }
+
+    /// Moves the selection by `delta` drawings, stopping at the ends.
+    pub fn select(&mut self, delta: isize) {
+        let last = self.drawings.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    /// Copies the selected drawing to `<name> copy.kli`, or `<name> copy 2.kli` and so on, and
+    /// selects the copy.
+    pub fn duplicate(&mut self) -> io::Result<String> {
+        let Some(drawing) = self.current() else {
+            return Err(io::Error::other("no drawing selected"));
+        };
+        let copy = (1..)
+            .map(|n| match n {
+                1 => format!("{} copy", drawing.name),
+                n => format!("{} copy {}", drawing.name, n),
+            })
+            .map(|name| self.dir.join(name + ".kli"))
+            .find(|path| !path.exists())
+            .expect("some name is free");
+        fs::copy(&drawing.path, &copy)?;
+        self.reload();
+        self.select_path(Some(&copy));
+        Ok(name_of(&copy))
+    }
+
+    /// Renames the selected drawing, returning its old and new paths.
+    pub fn rename(&mut self, name: &str) -> io::Result<(PathBuf, PathBuf)> {
+        let name = name.trim();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(io::Error::other(
+                "names can't be empty, start with . or contain slashes",
+            ));
+        }
+        let Some(drawing) = self.current() else {
+            return Err(io::Error::other("no drawing selected"));
+        };
+        let (from, to) = (drawing.path.clone(), self.dir.join(format!("{}.kli", name)));
+        if to != from && to.exists() {
+            return Err(io::Error::other(format!("{} already exists", name)));
+        }
+        fs::rename(&from, &to)?;
+        self.reload();
+        self.select_path(Some(&to));
+        Ok((from, to))
+    }
+
+    /// Deletes the selected drawing.
+    pub fn delete(&mut self) -> io::Result<()> {
+        if let Some(drawing) = self.current() {
+            fs::remove_file(&drawing.path)?;
+        }
+        self.reload();
+        Ok(())
+    }
+}
+
+fn name_of(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+}
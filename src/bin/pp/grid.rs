@@ -0,0 +1,490 @@
+use crate::{
+    history::History,
+    palette::EMPTY,
+    selection::{Area, Stamp},
+    shapes::{self, Shape, Tool},
+    symmetry::Symmetry,
+};
+use std::mem;
+
+pub type Cells = Vec<Vec<u8>>;
+
+/// Playback speed of new drawings, in frames per second.
+pub const DEFAULT_FPS: u32 = 8;
+pub const MAX_FPS: u32 = 30;
+
+/// What an undo step restores: all the frames and which one was being edited.
+type Snapshot = (Vec<Cells>, usize);
+
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    /// Animation frames, a still drawing has just one.
+    pub frames: Vec<Cells>,
+    /// Index of the frame being edited.
+    pub frame: usize,
+    pub fps: u32,
+    /// Whether the previous frame is shown faintly under the current one.
+    pub onion: bool,
+    pub cursor_x: usize,
+    pub cursor_y: usize,
+    pub autotoggle: bool,
+    /// Palette index painted by toggles.
+    pub color: u8,
+    pub shape: Option<Shape>,
+    /// Mirroring applied to toggles and strokes.
+    pub symmetry: Symmetry,
+    /// Anchor of the selection, which spans from it to the cursor.
+    pub selection: Option<(usize, usize)>,
+    pub clipboard: Option<Cells>,
+    /// Pasted or lifted cells following the cursor until committed.
+    pub floating: Option<Stamp>,
+    /// Picture shown faintly under the cells while tracing, never part of the drawing.
+    pub target: Option<Cells>,
+    /// Cursor of the other person drawing together, when they are on the same frame.
+    pub peer_cursor: Option<(usize, usize)>,
+    history: History<Snapshot>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            frames: vec![vec![vec![EMPTY; width]; height]],
+            frame: 0,
+            fps: DEFAULT_FPS,
+            onion: true,
+            cursor_x: 0,
+            cursor_y: 0,
+            autotoggle: false,
+            color: 1,
+            shape: None,
+            symmetry: Symmetry::Off,
+            selection: None,
+            clipboard: None,
+            floating: None,
+            target: None,
+            peer_cursor: None,
+            history: History::new(),
+        }
+    }
+
+    /// The cells of the frame being edited.
+    pub fn cells(&self) -> &Cells {
+        &self.frames[self.frame]
+    }
+
+    fn cells_mut(&mut self) -> &mut Cells {
+        &mut self.frames[self.frame]
+    }
+
+    /// Replaces the cells of the frame being edited, without recording an undo step.
+    pub fn set_cells(&mut self, cells: Cells) {
+        *self.cells_mut() = cells;
+    }
+
+    fn blank(&self) -> Cells {
+        vec![vec![EMPTY; self.width]; self.height]
+    }
+
+    /// Records the current frames as an undo step, to be called before every edit.
+    pub fn checkpoint(&mut self) {
+        self.history.push((self.frames.clone(), self.frame));
+    }
+
+    /// Paints the current cell with the active color, or clears it if it already has it.
+    pub fn toggle_current(&mut self) {
+        // in draw mode the toggle belongs to the ongoing stroke
+        if !self.autotoggle {
+            self.checkpoint();
+        }
+        self.toggle_cell();
+    }
+
+    fn toggle_cell(&mut self) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        // mirror images follow the cursor cell rather than toggling on their own
+        let value = if self.cells()[y][x] == self.color {
+            EMPTY
+        } else {
+            self.color
+        };
+        let images = self.symmetry.images(x, y, self.width, self.height);
+        let cells = self.cells_mut();
+        for (x, y) in images {
+            cells[y][x] = value;
+        }
+    }
+
+    /// Sets every cell on the line between two cells and their mirror images to `value`, without
+    /// recording an undo step.
+    pub fn paint_line(&mut self, from: (usize, usize), to: (usize, usize), value: u8) {
+        let (symmetry, width, height) = (self.symmetry, self.width, self.height);
+        let cells = self.cells_mut();
+        for (x, y) in shapes::line(from.0, from.1, to.0, to.1) {
+            for (x, y) in symmetry.images(x, y, width, height) {
+                cells[y][x] = value;
+            }
+        }
+        self.cursor_x = to.0;
+        self.cursor_y = to.1;
+    }
+
+    /// Paints the 4-connected region of same-colored cells under the cursor with the active color.
+    pub fn fill_current(&mut self) {
+        let color = self.color;
+        let (width, height) = (self.width, self.height);
+        let target = self.cells()[self.cursor_y][self.cursor_x];
+        if target == color {
+            return;
+        }
+        self.checkpoint();
+        let start = (self.cursor_x, self.cursor_y);
+        let cells = self.cells_mut();
+        // explicit stack instead of recursion, a full-screen region would overflow the call stack
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop() {
+            if cells[y][x] != target {
+                continue;
+            }
+            cells[y][x] = color;
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if x + 1 < width {
+                stack.push((x + 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+            if y + 1 < height {
+                stack.push((x, y + 1));
+            }
+        }
+    }
+
+    /// Anchors a shape at the cursor; picking the tool of the previewed shape again switches
+    /// between its outline and filled variants.
+    pub fn start_shape(&mut self, tool: Tool) {
+        self.set_autotoggle(false);
+        self.selection = None;
+        self.floating = None;
+        let tool = match (&self.shape, tool) {
+            (
+                Some(Shape {
+                    tool: Tool::Rect { filled },
+                    ..
+                }),
+                Tool::Rect { .. },
+            ) => Tool::Rect { filled: !filled },
+            (
+                Some(Shape {
+                    tool: Tool::Ellipse { filled },
+                    ..
+                }),
+                Tool::Ellipse { .. },
+            ) => Tool::Ellipse { filled: !filled },
+            _ => tool,
+        };
+        let anchor = match &self.shape {
+            Some(shape) => shape.anchor,
+            None => (self.cursor_x, self.cursor_y),
+        };
+        self.shape = Some(Shape { tool, anchor });
+    }
+
+    fn shape_points(&self) -> Vec<(usize, usize)> {
+        match &self.shape {
+            Some(shape) => shape
+                .tool
+                .points(shape.anchor, (self.cursor_x, self.cursor_y)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Paints the previewed shape with the active color.
+    pub fn commit_shape(&mut self) {
+        let points = self.shape_points();
+        if self.shape.take().is_some() {
+            self.checkpoint();
+            let color = self.color;
+            let cells = self.cells_mut();
+            for (x, y) in points {
+                cells[y][x] = color;
+            }
+        }
+    }
+
+    /// Anchors a selection at the cursor.
+    pub fn start_selection(&mut self) {
+        self.set_autotoggle(false);
+        self.shape = None;
+        self.floating = None;
+        self.selection = Some((self.cursor_x, self.cursor_y));
+    }
+
+    fn selection_area(&self) -> Option<Area> {
+        self.selection
+            .map(|anchor| Area::spanning(anchor, (self.cursor_x, self.cursor_y)))
+    }
+
+    /// The cells to highlight: the selection, or the extent of the floating stamp.
+    pub fn highlight(&self) -> Option<Area> {
+        if let Some(stamp) = &self.floating {
+            let height = stamp.cells.len();
+            let width = stamp.cells.first().map_or(0, Vec::len);
+            return Some(Area::spanning(
+                (self.cursor_x, self.cursor_y),
+                (
+                    (self.cursor_x + width - 1).min(self.width - 1),
+                    (self.cursor_y + height - 1).min(self.height - 1),
+                ),
+            ));
+        }
+        self.selection_area()
+    }
+
+    /// Copies the selected cells to the clipboard and ends the selection.
+    pub fn copy_selection(&mut self) -> bool {
+        let Some(area) = self.selection_area() else {
+            return false;
+        };
+        self.clipboard = Some(area.copy(self.cells()));
+        self.selection = None;
+        true
+    }
+
+    /// Copies the selected cells to the clipboard and clears them.
+    pub fn cut_selection(&mut self) -> bool {
+        let Some(area) = self.selection_area() else {
+            return false;
+        };
+        self.copy_selection();
+        self.checkpoint();
+        area.clear(self.cells_mut());
+        true
+    }
+
+    /// The selected cells, if there is a selection.
+    pub fn selected_cells(&self) -> Option<Cells> {
+        Some(self.selection_area()?.copy(self.cells()))
+    }
+
+    /// Floats the clipboard at the cursor.
+    pub fn paste(&mut self) -> bool {
+        let Some(cells) = self.clipboard.clone() else {
+            return false;
+        };
+        self.float(cells);
+        true
+    }
+
+    /// Floats `cells` at the cursor, to be placed like a paste.
+    pub fn float(&mut self, cells: Cells) {
+        self.set_autotoggle(false);
+        self.shape = None;
+        self.selection = None;
+        self.floating = Some(Stamp {
+            cells,
+            lifted_from: None,
+        });
+    }
+
+    /// Applies `transform` to the floating stamp, lifting the selection into one first.
+    pub fn transform(&mut self, transform: fn(&Cells) -> Cells) {
+        if let Some(area) = self.selection_area() {
+            self.floating = Some(Stamp {
+                cells: area.copy(self.cells()),
+                lifted_from: Some(area),
+            });
+            self.selection = None;
+            self.cursor_x = area.x0;
+            self.cursor_y = area.y0;
+        }
+        if let Some(stamp) = &mut self.floating {
+            stamp.cells = transform(&stamp.cells);
+        }
+    }
+
+    /// Draws the floating stamp where it is, its empty cells leave the canvas untouched.
+    pub fn commit_floating(&mut self) {
+        if let Some(stamp) = self.floating.take() {
+            self.checkpoint();
+            let (x, y) = (self.cursor_x, self.cursor_y);
+            stamp.draw(self.cells_mut(), x, y);
+        }
+    }
+
+    /// The cells as they should be shown, including previews of pending edits.
+    pub fn display_cells(&self) -> Cells {
+        let mut cells = self.cells().clone();
+        for (x, y) in self.shape_points() {
+            cells[y][x] = self.color;
+        }
+        if let Some(stamp) = &self.floating {
+            stamp.draw(&mut cells, self.cursor_x, self.cursor_y);
+        }
+        cells
+    }
+
+    /// The previous frame, looping around, when onion skinning is on.
+    pub fn onion_cells(&self) -> Option<&Cells> {
+        if !self.onion || self.frames.len() < 2 {
+            return None;
+        }
+        let previous = (self.frame + self.frames.len() - 1) % self.frames.len();
+        Some(&self.frames[previous])
+    }
+
+    /// Inserts an empty frame after the current one and moves to it.
+    pub fn add_frame(&mut self) {
+        self.set_autotoggle(false);
+        self.checkpoint();
+        let blank = self.blank();
+        self.frame += 1;
+        self.frames.insert(self.frame, blank);
+    }
+
+    /// Inserts a copy of the current frame after it and moves to it.
+    pub fn duplicate_frame(&mut self) {
+        self.set_autotoggle(false);
+        self.checkpoint();
+        let copy = self.cells().clone();
+        self.frame += 1;
+        self.frames.insert(self.frame, copy);
+    }
+
+    /// Removes the current frame; the last frame left is cleared instead.
+    pub fn delete_frame(&mut self) {
+        self.set_autotoggle(false);
+        self.checkpoint();
+        if self.frames.len() == 1 {
+            self.frames[0] = self.blank();
+            return;
+        }
+        self.frames.remove(self.frame);
+        self.frame = self.frame.min(self.frames.len() - 1);
+    }
+
+    /// Moves `delta` frames forward or backward, looping around.
+    pub fn step_frame(&mut self, delta: isize) {
+        self.set_autotoggle(false);
+        let len = self.frames.len() as isize;
+        self.frame = (self.frame as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Forgets the last undo step if nothing was edited since it was recorded.
+    pub fn discard_unchanged_checkpoint(&mut self) {
+        self.history
+            .discard_if_unchanged(&(self.frames.clone(), self.frame));
+    }
+
+    /// Enters or leaves draw mode; everything drawn in between is a single undo step.
+    pub fn set_autotoggle(&mut self, autotoggle: bool) {
+        if autotoggle == self.autotoggle {
+            return;
+        }
+        if autotoggle {
+            self.checkpoint();
+        } else {
+            self.discard_unchanged_checkpoint();
+        }
+        self.autotoggle = autotoggle;
+    }
+
+    /// Sets a cell changed by the other person drawing together. The change is made in the undo
+    /// history too, so undoing only takes back this side's own edits.
+    pub fn set_remote_cell(&mut self, frame: usize, x: usize, y: usize, value: u8) {
+        let set = |frames: &mut Vec<Cells>| {
+            if let Some(cell) = frames
+                .get_mut(frame)
+                .and_then(|cells| cells.get_mut(y)?.get_mut(x))
+            {
+                *cell = value;
+            }
+        };
+        set(&mut self.frames);
+        self.history.rebase(|(frames, _)| set(frames));
+    }
+
+    /// Replaces all the frames with the other person's; the undo history is dropped as its
+    /// steps no longer apply.
+    pub fn set_remote_frames(&mut self, frames: Vec<Cells>) {
+        self.frames = frames;
+        self.frame = self.frame.min(self.frames.len() - 1);
+        self.history.clear();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.set_autotoggle(false);
+        let mut state = (mem::take(&mut self.frames), self.frame);
+        let undone = self.history.undo(&mut state);
+        (self.frames, self.frame) = state;
+        undone
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.set_autotoggle(false);
+        let mut state = (mem::take(&mut self.frames), self.frame);
+        let redone = self.history.redo(&mut state);
+        (self.frames, self.frame) = state;
+        redone
+    }
+
+    pub fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let new_x = (self.cursor_x as isize + dx)
+            .max(0)
+            .min(self.width as isize - 1) as usize;
+        let new_y = (self.cursor_y as isize + dy)
+            .max(0)
+            .min(self.height as isize - 1) as usize;
+        self.cursor_x = new_x;
+        self.cursor_y = new_y;
+        if self.autotoggle {
+            self.toggle_cell();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_stays_inside_walls() {
+        let mut grid = Grid::new(4, 3);
+        grid.color = 2;
+        for y in 0..3 {
+            grid.frames[0][y][2] = 1;
+        }
+        grid.fill_current();
+        assert_eq!(grid.cells()[0], [2, 2, 1, EMPTY]);
+        assert_eq!(grid.cells()[2], [2, 2, 1, EMPTY]);
+        grid.undo();
+        assert_eq!(grid.cells()[0], [EMPTY, EMPTY, 1, EMPTY]);
+    }
+
+    #[test]
+    fn undo_keeps_remote_edits() {
+        let mut grid = Grid::new(3, 1);
+        grid.toggle_current();
+        grid.set_remote_cell(0, 2, 0, 5);
+        grid.undo();
+        assert_eq!(grid.cells()[0], [EMPTY, EMPTY, 5]);
+        grid.redo();
+        assert_eq!(grid.cells()[0], [1, EMPTY, 5]);
+        // out of range edits are ignored
+        grid.set_remote_cell(1, 0, 0, 5);
+        grid.set_remote_cell(0, 3, 0, 5);
+        assert_eq!(grid.frames, [vec![vec![1, EMPTY, 5]]]);
+    }
+
+    #[test]
+    fn filling_with_the_same_color_records_nothing() {
+        let mut grid = Grid::new(2, 2);
+        grid.color = EMPTY;
+        grid.fill_current();
+        assert!(!grid.undo());
+    }
+}
@@ -0,0 +1,108 @@
+use std::{collections::VecDeque, mem};
+
+/// Number of undo steps kept, older ones are forgotten.
+const DEPTH: usize = 100;
+
+/// Undo/redo stacks of whole-state snapshots.
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    /// The redo stack dropped by the last push, given back if that step is discarded.
+    dropped_redo: Vec<T>,
+}
+
+impl<T: PartialEq> History<T> {
+    pub fn new() -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            dropped_redo: Vec::new(),
+        }
+    }
+
+    /// Records the state before an edit; the redo stack is dropped.
+    pub fn push(&mut self, snapshot: T) {
+        if self.undo.len() == DEPTH {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(snapshot);
+        self.dropped_redo = mem::take(&mut self.redo);
+    }
+
+    /// Forgets the last recorded step when the edit it was taken for didn't change anything,
+    /// the redo stack it dropped comes back.
+    pub fn discard_if_unchanged(&mut self, current: &T) {
+        if self.undo.back() == Some(current) {
+            self.undo.pop_back();
+            self.redo = mem::take(&mut self.dropped_redo);
+        }
+    }
+
+    /// Changes every recorded state the same way, for edits made from outside that undo must
+    /// keep.
+    pub fn rebase(&mut self, mut change: impl FnMut(&mut T)) {
+        self.undo
+            .iter_mut()
+            .chain(&mut self.redo)
+            .chain(&mut self.dropped_redo)
+            .for_each(&mut change);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.dropped_redo.clear();
+    }
+
+    /// Swaps `current` with the state before the last edit, returns false if there is none.
+    pub fn undo(&mut self, current: &mut T) -> bool {
+        self.dropped_redo.clear();
+        match self.undo.pop_back() {
+            Some(previous) => {
+                self.redo.push(mem::replace(current, previous));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, current: &mut T) -> bool {
+        self.dropped_redo.clear();
+        match self.redo.pop() {
+            Some(next) => {
+                self.undo.push_back(mem::replace(current, next));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discarded_step_keeps_redo() {
+        let mut history = History::new();
+        let mut state = 1;
+        history.push(state);
+        state = 2;
+        assert!(history.undo(&mut state));
+        assert_eq!(state, 1);
+
+        // an edit that changed nothing
+        history.push(state);
+        history.discard_if_unchanged(&state);
+        assert!(history.redo(&mut state));
+        assert_eq!(state, 2);
+
+        // a kept edit still drops it
+        assert!(history.undo(&mut state));
+        history.push(state);
+        state = 3;
+        history.discard_if_unchanged(&state);
+        assert!(!history.redo(&mut state));
+        assert_eq!(state, 3);
+    }
+}
@@ -0,0 +1,133 @@
+//! Conway's Game of Life played on a frame of the drawing, where every painted cell is alive.
+
+use crate::{grid::Cells, palette::EMPTY};
+
+/// Generations per second when a simulation starts.
+pub const DEFAULT_SPEED: u32 = 8;
+
+pub struct Life {
+    /// The frame as it was before the simulation, put back when leaving.
+    pub snapshot: Cells,
+    pub running: bool,
+    /// Whether the edges wrap around; otherwise everything past them is dead.
+    pub wrap: bool,
+    pub generation: usize,
+    /// Generations per second while running.
+    pub speed: u32,
+}
+
+impl Life {
+    pub fn new(snapshot: Cells) -> Self {
+        Life {
+            snapshot,
+            running: false,
+            wrap: true,
+            generation: 0,
+            speed: DEFAULT_SPEED,
+        }
+    }
+
+    /// Steps `cells` one generation, pausing once nothing changes any more.
+    pub fn advance(&mut self, cells: &mut Cells) {
+        let next = step(cells, self.wrap);
+        if next == *cells {
+            self.running = false;
+        } else {
+            *cells = next;
+            self.generation += 1;
+        }
+    }
+}
+
+/// The next generation: live cells with 2 or 3 live neighbours survive with their color, dead
+/// cells with exactly 3 are born with the color most of those neighbours have.
+pub fn step(cells: &Cells, wrap: bool) -> Cells {
+    let height = cells.len() as isize;
+    let width = cells.first().map_or(0, Vec::len) as isize;
+    let neighbour = |x: isize, y: isize| -> u8 {
+        let (x, y) = if wrap {
+            (x.rem_euclid(width), y.rem_euclid(height))
+        } else if (0..width).contains(&x) && (0..height).contains(&y) {
+            (x, y)
+        } else {
+            return EMPTY;
+        };
+        cells[y as usize][x as usize]
+    };
+
+    let mut next = cells.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let mut colors = Vec::with_capacity(8);
+            for (dx, dy) in [
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ] {
+                let cell = neighbour(x + dx, y + dy);
+                if cell != EMPTY {
+                    colors.push(cell);
+                }
+            }
+            let cell = &mut next[y as usize][x as usize];
+            *cell = match (*cell, colors.len()) {
+                (EMPTY, 3) => majority(&colors),
+                (EMPTY, _) => EMPTY,
+                (alive, 2 | 3) => alive,
+                _ => EMPTY,
+            };
+        }
+    }
+    next
+}
+
+/// The most frequent color, the lowest one on ties.
+fn majority(colors: &[u8]) -> u8 {
+    let count = |c: u8| colors.iter().filter(|&&other| other == c).count();
+    colors
+        .iter()
+        .copied()
+        .max_by_key(|&c| (count(c), std::cmp::Reverse(c)))
+        .unwrap_or(EMPTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rows: &[&str]) -> Cells {
+        rows.iter()
+            .map(|row| {
+                row.bytes()
+                    .map(|b| if b == b'.' { EMPTY } else { b - b'0' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let vertical = cells(&[".....", "..1..", "..1..", "..1..", "....."]);
+        let horizontal = cells(&[".....", ".....", ".111.", ".....", "....."]);
+        assert_eq!(step(&vertical, false), horizontal);
+        assert_eq!(step(&horizontal, false), vertical);
+    }
+
+    #[test]
+    fn births_take_the_majority_color() {
+        let next = step(&cells(&["22.", "3..", "..."]), false);
+        assert_eq!(next[1][1], 2);
+    }
+
+    #[test]
+    fn wrapping_joins_the_edges() {
+        let edge = cells(&["1...", "1...", "1...", "...."]);
+        assert_eq!(step(&edge, false)[1], [1, 1, EMPTY, EMPTY]);
+        assert_eq!(step(&edge, true)[1], [1, 1, EMPTY, 1]);
+    }
+}
@@ -0,0 +1,1489 @@
+mod autosave;
+mod export;
+mod file;
+mod font;
+mod gallery;
+mod grid;
+mod history;
+mod life;
+mod net;
+mod nonogram;
+mod palette;
+mod picture;
+mod render;
+mod selection;
+mod shapes;
+mod stamps;
+mod symmetry;
+mod trace;
+mod turtle;
+
+use autosave::Autosave;
+use clap::Parser;
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use gallery::{Gallery, Prompt};
+use grid::{Grid, MAX_FPS};
+use life::Life;
+use net::Session;
+use nonogram::Puzzle;
+use picture::Conversion;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame, Terminal,
+};
+use render::{RenderMode, Viewport};
+use selection::Stamp;
+use shapes::Tool;
+use stamps::Library;
+use std::{fs, io, mem, path::PathBuf, process, time::Duration};
+use trace::Trace;
+use turtle::{Run, Turtle};
+
+const DEFAULT_PATH: &str = "drawing.kli";
+/// Smallest terminal the editor is drawn in, below it only a notice is shown.
+const MIN_WIDTH: u16 = 24;
+const MIN_HEIGHT: u16 = 8;
+/// Size of a drawing's tile in the gallery, borders included.
+const TILE_WIDTH: u16 = 24;
+const TILE_HEIGHT: u16 = 10;
+/// Width of the stamp library panel, borders included.
+const STAMP_PANEL_WIDTH: u16 = 18;
+const KEYS: &[(&str, &str)] = &[
+    ("arrows / hjkl", "move the cursor"),
+    ("Space", "toggle the cell"),
+    ("Ctrl+Space", "draw mode: toggle while moving"),
+    ("1-9", "pick a color"),
+    ("F", "fill the area under the cursor"),
+    ("I / R / O", "line / rectangle / ellipse, again for filled"),
+    ("W", "write text, Tab for double size"),
+    ("V", "select from the cursor"),
+    ("Ctrl+C / X / V", "copy / cut / paste selection"),
+    ("| / _ / T", "mirror / flip / rotate selection"),
+    ("B", "browse stamps"),
+    ("S", "save selection as a stamp"),
+    ("Y", "symmetry: horizontal / vertical / four-way / off"),
+    ("M", "switch render mode"),
+    ("A / D / X", "add / duplicate / delete frame"),
+    (", / .", "previous / next frame"),
+    ("N", "onion skin on/off"),
+    ("P", "play the animation"),
+    ("G", "Game of Life on the frame"),
+    (":", "turtle program, e.g. repeat 4 [fd 5 rt 90]"),
+    ("+ / -", "animation speed"),
+    ("Ctrl+Z / Ctrl+Y", "undo / redo"),
+    ("Ctrl+S", "save"),
+    ("Ctrl+O", "gallery of saved drawings"),
+    (
+        "Ctrl+E / Ctrl+G / Ctrl+T",
+        "export PNG / animated GIF / ANSI text",
+    ),
+    ("Esc", "cancel / quit"),
+];
+
+#[derive(Parser)]
+struct Args {
+    /// Drawing to open, created on first save if it doesn't exist (default: a new drawing in the
+    /// gallery)
+    file: Option<PathBuf>,
+
+    /// Start in the gallery of saved drawings
+    #[arg(long, conflicts_with_all = ["export", "puzzle"])]
+    gallery: bool,
+
+    /// Write the drawing to a PNG image, or an animated GIF, ANSI colored text or plain text if
+    /// OUT ends in .gif, .ans or .txt, and exit without opening the editor
+    #[arg(long, value_name = "OUT", requires = "file")]
+    export: Option<PathBuf>,
+
+    /// Size in pixels of one cell in exported images
+    #[arg(long, default_value_t = 16)]
+    scale: usize,
+
+    /// Delay between frames of exported GIFs in milliseconds (default: the drawing's speed)
+    #[arg(long)]
+    delay: Option<u32>,
+
+    /// How many times exported GIFs play, 0 loops forever
+    #[arg(long, default_value_t = 0)]
+    loops: u16,
+
+    /// Character of filled cells in plain text exports
+    #[arg(long, default_value_t = '#')]
+    on: char,
+
+    /// Character of empty cells in plain text exports
+    #[arg(long, default_value_t = '.')]
+    off: char,
+
+    /// Print the drawing as colored text when leaving the editor
+    #[arg(long)]
+    print_on_exit: bool,
+
+    /// Play FILE as a nonogram puzzle: fill the cells the row and column clues point to
+    #[arg(long, requires = "file", conflicts_with_all = ["export", "import"])]
+    puzzle: bool,
+
+    /// Draw over a faint picture and get scored on how closely it is traced: a drawing file, a
+    /// letter or digit, or one of circle, square, triangle, heart, star, house, cat, fish, duck
+    #[arg(long, value_name = "TARGET", conflicts_with_all = ["export", "import", "puzzle"])]
+    trace: Option<String>,
+
+    /// Run a turtle program from SCRIPT on the drawing once the editor opens, starting from the
+    /// middle of the canvas
+    #[arg(long, value_name = "SCRIPT", conflicts_with_all = ["export", "puzzle"])]
+    turtle: Option<PathBuf>,
+
+    /// Let someone draw along from another terminal with --join, listening on PORT (default:
+    /// 7700)
+    #[arg(
+        long,
+        value_name = "PORT",
+        num_args = 0..=1,
+        default_missing_value = "7700",
+        conflicts_with_all = ["export", "puzzle", "gallery"]
+    )]
+    host: Option<u16>,
+
+    /// Draw along with someone who ran --host, at ADDR such as 192.168.1.20:7700; the drawing
+    /// is kept as a new drawing in the gallery
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["file", "import", "export", "puzzle", "gallery", "host", "width", "height"]
+    )]
+    join: Option<String>,
+
+    /// Start a new drawing from a PNG, PBM, PGM or PPM image, saved as FILE or next to the
+    /// image with a .kli extension
+    #[arg(long, value_name = "IMAGE", conflicts_with = "export")]
+    import: Option<PathBuf>,
+
+    /// How imported images are turned into cells
+    #[arg(long, value_enum, default_value_t = Conversion::FloydSteinberg)]
+    convert: Conversion,
+
+    /// Brightness below which pixels become black with --convert threshold
+    #[arg(long, default_value_t = 128)]
+    threshold: u8,
+
+    /// Canvas width in cells for a new drawing (default: fit the terminal)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    width: Option<u16>,
+
+    /// Canvas height in cells for a new drawing (default: fit the terminal)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    height: Option<u16>,
+}
+
+struct App {
+    grid: Grid,
+    path: PathBuf,
+    export: export::Options,
+    status: Option<String>,
+    render_mode: RenderMode,
+    view: Viewport,
+    /// Where the grid was last drawn, to map mouse positions to cells.
+    grid_area: Rect,
+    /// Last cell of the ongoing mouse stroke.
+    mouse_last: Option<(usize, usize)>,
+    /// Whether the terminal was too small to show the editor on the last draw.
+    too_small: bool,
+    show_help: bool,
+    /// Frame on screen while the animation plays.
+    playing: Option<usize>,
+    life: Option<Life>,
+    puzzle: Option<Puzzle>,
+    trace: Option<Trace>,
+    autosave: Option<Autosave>,
+    /// Unsaved drawing of an earlier session, offered until accepted or declined.
+    recovered: Option<Grid>,
+    /// Text being typed with the text tool, previewed as a floating stamp.
+    text: Option<String>,
+    /// Whether typed text is drawn at double size.
+    text_double: bool,
+    /// Saved drawings shown instead of the editor.
+    gallery: Option<Gallery>,
+    /// Stamp library open in the side panel, its current stamp floating at the cursor.
+    stamps: Option<Library>,
+    /// Turtle program being typed.
+    command_line: Option<String>,
+    /// Last program run from the command line, brought back with Up.
+    last_command: String,
+    /// Turtle program being played.
+    run: Option<Run>,
+    /// Where the last program left the turtle.
+    turtle: Option<Turtle>,
+    /// Connection to the other person when drawing together.
+    session: Option<Session>,
+}
+
+impl App {
+    /// The first cell under a character of the grid; in modes with several cells per character
+    /// the mouse paints them all, see [`App::paint_chars`].
+    fn cell_at(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let area = self.grid_area;
+        if column < area.x || row < area.y || column >= area.right() || row >= area.bottom() {
+            return None;
+        }
+        let (sx, sy) = self.render_mode.cells_per_char();
+        let x = self.view.x + (column - area.x) as usize * sx;
+        let y = self.view.y + (row - area.y) as usize * sy;
+        (x < self.grid.width && y < self.grid.height).then_some((x, y))
+    }
+
+    /// Rasterizes the typed text into the floating stamp.
+    fn update_text_preview(&mut self) {
+        let text = self.text.as_deref().unwrap_or_default();
+        let scale = if self.text_double { 2 } else { 1 };
+        self.grid.floating = (!text.is_empty()).then(|| Stamp {
+            cells: font::render(text, self.grid.color, scale),
+            lifted_from: None,
+        });
+    }
+
+    /// Switches to another drawing; unsaved changes to the current one stay in its autosave.
+    fn open_drawing(&mut self, path: PathBuf, grid: Grid) {
+        if let Some(autosave) = self.autosave.take() {
+            autosave.finish(&self.grid);
+        }
+        self.autosave = Autosave::new(&path, &grid);
+        self.recovered = self.autosave.as_mut().and_then(Autosave::recover);
+        self.status = Some(format!("Opened {}", path.display()));
+        self.grid = grid;
+        self.path = path;
+        self.trace = None;
+        self.turtle = None;
+    }
+
+    fn open_gallery(&mut self) {
+        if self.session.is_some() {
+            self.status = Some("The gallery is closed while drawing together".to_string());
+            return;
+        }
+        match gallery::dir() {
+            Some(dir) => self.gallery = Some(Gallery::open(dir, &self.path)),
+            None => self.status = Some("There is no pictures directory to look in".to_string()),
+        }
+    }
+
+    /// Handles a key in the gallery.
+    fn gallery_key(&mut self, key: KeyEvent) {
+        let Some(gallery) = &mut self.gallery else {
+            return;
+        };
+        let mut open = None;
+        match &mut gallery.prompt {
+            Some(Prompt::Rename(name)) => match key.code {
+                KeyCode::Esc => gallery.prompt = None,
+                KeyCode::Enter => {
+                    let name = mem::take(name);
+                    gallery.prompt = None;
+                    self.status = Some(match gallery.rename(&name) {
+                        Ok((from, to)) => {
+                            if from == self.path {
+                                if let Some(autosave) = &mut self.autosave {
+                                    autosave.rename(&to);
+                                }
+                                self.path = to;
+                            }
+                            format!("Renamed to {}", name.trim())
+                        }
+                        Err(err) => format!("Rename failed: {}", err),
+                    });
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) => name.push(c),
+                _ => {}
+            },
+            Some(Prompt::Delete) => {
+                gallery.prompt = None;
+                if key.code == KeyCode::Char('y') {
+                    self.status = gallery
+                        .delete()
+                        .err()
+                        .map(|err| format!("Delete failed: {}", err));
+                }
+            }
+            None => match key.code {
+                KeyCode::Esc => self.gallery = None,
+                KeyCode::Left | KeyCode::Char('h') => gallery.select(-1),
+                KeyCode::Right | KeyCode::Char('l') => gallery.select(1),
+                KeyCode::Up | KeyCode::Char('k') => gallery.select(-(gallery.columns as isize)),
+                KeyCode::Down | KeyCode::Char('j') => gallery.select(gallery.columns as isize),
+                KeyCode::Enter => open = gallery.current().map(|drawing| drawing.path.clone()),
+                KeyCode::Char('d') => {
+                    self.status = Some(match gallery.duplicate() {
+                        Ok(name) => format!("Duplicated as {}", name),
+                        Err(err) => format!("Duplicate failed: {}", err),
+                    });
+                }
+                KeyCode::Char('r') => {
+                    gallery.prompt = gallery
+                        .current()
+                        .map(|drawing| Prompt::Rename(drawing.name.clone()));
+                }
+                KeyCode::Char('x') | KeyCode::Delete if gallery.current().is_some() => {
+                    gallery.prompt = Some(Prompt::Delete);
+                }
+                _ => {}
+            },
+        }
+        let Some(path) = open else {
+            return;
+        };
+        self.gallery = None;
+        if path == self.path {
+            return;
+        }
+        match file::load(&path) {
+            Ok(grid) => self.open_drawing(path, grid),
+            Err(err) => self.status = Some(format!("Cannot open {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Starts playing a turtle program as a single undoable edit, carrying on from where the last
+    /// one stopped unless the cursor moved away since.
+    fn start_run(&mut self, program: &turtle::Program) {
+        let grid = &mut self.grid;
+        grid.set_autotoggle(false);
+        grid.checkpoint();
+        let turtle = self
+            .turtle
+            .filter(|turtle| turtle.is_at_cursor(grid))
+            .unwrap_or_else(|| Turtle::new(grid.cursor_x, grid.cursor_y));
+        self.run = Some(Run::new(program, turtle));
+    }
+
+    /// Ends the program being played, keeping what it drew.
+    fn stop_run(&mut self) {
+        if let Some(run) = self.run.take() {
+            self.turtle = Some(run.turtle);
+            self.grid.discard_unchanged_checkpoint();
+        }
+    }
+
+    /// Paints the cells of every character on the line between the characters whose first cells
+    /// are `from` and `to`, without recording an undo step, and leaves the cursor on `to`.
+    fn paint_chars(&mut self, from: (usize, usize), to: (usize, usize), value: u8) {
+        let (sx, sy) = self.render_mode.cells_per_char();
+        let (width, height) = (self.grid.width, self.grid.height);
+        for dy in 0..sy {
+            for dx in 0..sx {
+                let (from_x, from_y) = (from.0 + dx, from.1 + dy);
+                let (to_x, to_y) = (to.0 + dx, to.1 + dy);
+                if from_x.max(to_x) < width && from_y.max(to_y) < height {
+                    self.grid.paint_line((from_x, from_y), (to_x, to_y), value);
+                }
+            }
+        }
+        (self.grid.cursor_x, self.grid.cursor_y) = to;
+    }
+
+    /// Left button toggles on click and paints with the active color on drag, right button erases.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let cell = self.cell_at(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(button) => {
+                let Some((x, y)) = cell else { return };
+                self.grid.set_autotoggle(false);
+                // a click toggles like Space, on every cell of the character clicked
+                let value = match button {
+                    MouseButton::Left if self.grid.cells()[y][x] == self.grid.color => {
+                        palette::EMPTY
+                    }
+                    MouseButton::Left => self.grid.color,
+                    MouseButton::Right => palette::EMPTY,
+                    MouseButton::Middle => return,
+                };
+                self.grid.checkpoint();
+                self.paint_chars((x, y), (x, y), value);
+                self.mouse_last = Some((x, y));
+            }
+            MouseEventKind::Drag(button) => {
+                let (Some(last), Some(cell)) = (self.mouse_last, cell) else {
+                    return;
+                };
+                let value = match button {
+                    MouseButton::Left => self.grid.color,
+                    MouseButton::Right => palette::EMPTY,
+                    MouseButton::Middle => return,
+                };
+                self.paint_chars(last, cell, value);
+                self.mouse_last = Some(cell);
+            }
+            MouseEventKind::Up(_) if self.mouse_last.is_some() => {
+                self.mouse_last = None;
+                self.grid.discard_unchanged_checkpoint();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    let path = match (args.file, &args.import) {
+        (Some(file), _) => file,
+        (None, Some(image)) => image.with_extension("kli"),
+        // new drawings go to the gallery so they can be found again without a path
+        (None, None) => gallery::dir()
+            .filter(|dir| fs::create_dir_all(dir).is_ok())
+            .map_or_else(
+                || PathBuf::from(DEFAULT_PATH),
+                |dir| gallery::new_drawing_path(&dir),
+            ),
+    };
+    let export_options = export::Options {
+        scale: args.scale,
+        delay_ms: args.delay,
+        loops: args.loops,
+        on: args.on,
+        off: args.off,
+    };
+
+    if let Some(out) = args.export {
+        let res = file::load(&path).and_then(|grid| export::write(&grid, &out, &export_options));
+        if let Err(err) = res {
+            eprintln!("pp: cannot export {}: {}", path.display(), err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // a new canvas fits the terminal, reserving space for borders and palette
+    let (columns, rows) = crossterm::terminal::size()?;
+    let new_width = args.width.unwrap_or(columns.saturating_sub(2).max(1)) as usize;
+    let new_height = args.height.unwrap_or(rows.saturating_sub(3).max(1)) as usize;
+
+    // load before touching the terminal so errors are printed on a sane screen
+    let mut status = None;
+    let mut session = None;
+    let mut grid = if let Some(address) = &args.join {
+        match Session::join(address) {
+            Ok((joined, grid)) => {
+                status = Some(format!("Joined {}, draw together!", address));
+                session = Some(joined);
+                grid
+            }
+            Err(err) => {
+                eprintln!("pp: cannot join {}: {}", address, err);
+                process::exit(1);
+            }
+        }
+    } else if let Some(image) = &args.import {
+        match picture::load(image) {
+            Ok(picture) => {
+                status = Some(format!("Imported {}", image.display()));
+                picture::to_grid(
+                    &picture,
+                    new_width,
+                    new_height,
+                    args.convert,
+                    args.threshold,
+                )
+            }
+            Err(err) => {
+                eprintln!("pp: cannot import {}: {}", image.display(), err);
+                process::exit(1);
+            }
+        }
+    } else if path.exists() || args.puzzle {
+        if args.width.is_some() || args.height.is_some() {
+            eprintln!(
+                "pp: {} already exists and keeps its size, leave out --width and --height",
+                path.display()
+            );
+            process::exit(1);
+        }
+        match file::load(&path) {
+            Ok(grid) => grid,
+            Err(err) => {
+                eprintln!("pp: cannot open {}: {}", path.display(), err);
+                process::exit(1);
+            }
+        }
+    } else {
+        Grid::new(new_width, new_height)
+    };
+
+    let trace = args.trace.map(|name| match trace::load(&name) {
+        Ok(target) => {
+            grid.target = Some(trace::fit(&target, grid.width, grid.height));
+            Trace { name, result: None }
+        }
+        Err(err) => {
+            eprintln!("pp: cannot trace {}: {}", name, err);
+            process::exit(1);
+        }
+    });
+    let program = args.turtle.as_ref().map(|script| {
+        let res = std::fs::read_to_string(script).map_err(|err| err.to_string());
+        match res.and_then(|source| turtle::parse(&source).map_err(|err| err.to_string())) {
+            Ok(program) => program,
+            Err(err) => {
+                eprintln!("pp: {}:{}", script.display(), err);
+                process::exit(1);
+            }
+        }
+    });
+    let puzzle = if args.puzzle {
+        match Puzzle::new(grid.cells().clone()) {
+            Ok(puzzle) => Some(puzzle),
+            Err(msg) => {
+                eprintln!("pp: {} can't be a puzzle: {}", path.display(), msg);
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    // puzzles are played on a blank canvas the size of the picture and leave the drawing alone
+    let (grid, mut autosave) = match &puzzle {
+        Some(puzzle) => (Grid::new(puzzle.width(), puzzle.height()), None),
+        None => {
+            let autosave = Autosave::new(&path, &grid);
+            (grid, autosave)
+        }
+    };
+    let recovered = autosave.as_mut().and_then(Autosave::recover);
+    if let Some(port) = args.host {
+        match Session::host(port, &grid) {
+            Ok(host) => session = Some(host),
+            Err(err) => {
+                eprintln!("pp: cannot host on port {}: {}", port, err);
+                process::exit(1);
+            }
+        }
+    }
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut app = App {
+        grid,
+        path,
+        export: export_options,
+        status,
+        render_mode: RenderMode::Cells,
+        view: Viewport::default(),
+        grid_area: Rect::default(),
+        mouse_last: None,
+        too_small: false,
+        show_help: false,
+        playing: None,
+        life: None,
+        puzzle,
+        trace,
+        autosave,
+        recovered,
+        text: None,
+        text_double: false,
+        gallery: None,
+        stamps: None,
+        command_line: None,
+        last_command: String::new(),
+        run: None,
+        turtle: None,
+        session,
+    };
+    if args.gallery {
+        app.open_gallery();
+    }
+    if let Some(program) = program {
+        app.grid.cursor_x = app.grid.width / 2;
+        app.grid.cursor_y = app.grid.height / 2;
+        app.start_run(&program);
+    }
+
+    let res = run_app(&mut terminal, &mut app);
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+    if let Some(autosave) = app.autosave.take() {
+        autosave.finish(&app.grid);
+    }
+    if args.print_on_exit {
+        print!("{}", export::ansi(app.grid.cells()));
+    }
+    res
+}
+
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    loop {
+        // the board of a Life simulation isn't the drawing, it is shared on leaving
+        if let Some(session) = app.session.as_mut().filter(|_| app.life.is_none()) {
+            if let Some(news) = session.sync(&mut app.grid) {
+                app.status = Some(news);
+            }
+            // the other person may have deleted the frame being played
+            app.playing = app.playing.filter(|&frame| frame < app.grid.frames.len());
+        }
+        terminal.draw(|f| ui(f, app))?;
+        // the board of a Life simulation isn't the drawing, it is put back on leaving
+        let autosave = app.autosave.as_mut().filter(|_| app.life.is_none());
+        if let Some(autosave) = autosave {
+            autosave.tick(&app.grid, false);
+        }
+
+        if let Some(frame) = app.playing {
+            let delay = Duration::from_millis(1000 / app.grid.fps as u64);
+            if !event::poll(delay)? {
+                app.playing = Some((frame + 1) % app.grid.frames.len());
+                continue;
+            }
+        }
+        if let Some(run) = &mut app.run {
+            if !event::poll(Duration::from_millis(1000 / turtle::SPEED))? {
+                if !run.advance(&mut app.grid) {
+                    app.stop_run();
+                }
+                continue;
+            }
+        }
+        if let Some(life) = app.life.as_mut().filter(|life| life.running) {
+            let delay = Duration::from_millis(1000 / life.speed as u64);
+            if !event::poll(delay)? {
+                let mut cells = app.grid.cells().clone();
+                life.advance(&mut cells);
+                app.grid.set_cells(cells);
+                continue;
+            }
+        }
+        if app.gallery.as_ref().is_some_and(Gallery::is_animated) {
+            // redraw for the next frame of animated thumbnails
+            if !event::poll(Duration::from_millis(1000 / MAX_FPS as u64))? {
+                continue;
+            }
+        }
+        if app.session.is_some() {
+            // wake up for what the other person does
+            if !event::poll(net::POLL)? {
+                continue;
+            }
+        }
+        if let Some(autosave) = app.autosave.as_ref().filter(|_| app.life.is_none()) {
+            // wake up for the next autosave instead of blocking until input comes
+            if !event::poll(autosave.time_left())? {
+                continue;
+            }
+        }
+
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                if app.playing.is_none()
+                    && app.life.is_none()
+                    && app.recovered.is_none()
+                    && app.puzzle.is_none()
+                    && app.text.is_none()
+                    && app.command_line.is_none()
+                    && app.stamps.is_none()
+                    && app.gallery.is_none()
+                    && app.run.is_none()
+                    && app
+                        .trace
+                        .as_ref()
+                        .is_none_or(|trace| trace.result.is_none())
+                    && !app.show_help
+                {
+                    app.handle_mouse(mouse);
+                }
+                continue;
+            }
+            Event::Resize(_, _) => {
+                // the canvas keeps its size, the next draw re-lays the view out around it
+                app.mouse_last = None;
+                terminal.autoresize()?;
+                continue;
+            }
+            _ => continue,
+        };
+        if app.too_small && key.code != KeyCode::Esc {
+            // don't let edits happen blindly
+            continue;
+        }
+        app.status = None;
+        if let Some(recovered) = app.recovered.take() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    // only the drawing is restored, the session's tools and tracing carry on
+                    let mut recovered = recovered;
+                    recovered.color = app.grid.color;
+                    recovered.symmetry = app.grid.symmetry;
+                    recovered.target = app.trace.as_ref().and_then(|trace| {
+                        let target = trace::load(&trace.name).ok()?;
+                        Some(trace::fit(&target, recovered.width, recovered.height))
+                    });
+                    app.grid = recovered;
+                    app.status = Some("Restored the unsaved drawing".to_string());
+                }
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    if let Some(autosave) = &mut app.autosave {
+                        autosave.discard();
+                    }
+                }
+                _ => app.recovered = Some(recovered),
+            }
+            continue;
+        }
+        if app.show_help {
+            app.show_help = false;
+            continue;
+        }
+        if let Some(trace) = app.trace.as_mut().filter(|trace| trace.result.is_some()) {
+            trace.result = None;
+            continue;
+        }
+        if app.gallery.is_some() {
+            app.gallery_key(key);
+            continue;
+        }
+        let grid = &mut app.grid;
+        if let Some(puzzle) = &mut app.puzzle {
+            let (x, y) = (grid.cursor_x, grid.cursor_y);
+            match key.code {
+                KeyCode::Esc => break,
+                _ if puzzle.solved => {}
+                KeyCode::Char(' ') => {
+                    puzzle.marks[y][x] = false;
+                    grid.toggle_current();
+                }
+                KeyCode::Char('x') if grid.cells()[y][x] == palette::EMPTY => {
+                    puzzle.marks[y][x] = !puzzle.marks[y][x];
+                }
+                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.status = (!grid.undo()).then(|| "Nothing to undo".to_string());
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.status = (!grid.redo()).then(|| "Nothing to redo".to_string());
+                }
+                KeyCode::Up | KeyCode::Char('k') => grid.move_cursor(0, -1),
+                KeyCode::Down | KeyCode::Char('j') => grid.move_cursor(0, 1),
+                KeyCode::Left | KeyCode::Char('h') => grid.move_cursor(-1, 0),
+                KeyCode::Right | KeyCode::Char('l') => grid.move_cursor(1, 0),
+                _ => {}
+            }
+            puzzle.solved |= puzzle.is_solved_by(grid.cells());
+            continue;
+        }
+        if app.playing.is_some() {
+            match key.code {
+                KeyCode::Char('+') => grid.fps = (grid.fps + 1).min(MAX_FPS),
+                KeyCode::Char('-') => grid.fps = (grid.fps - 1).max(1),
+                _ => app.playing = None,
+            }
+            continue;
+        }
+        if let Some(run) = &mut app.run {
+            match key.code {
+                KeyCode::Esc => app.stop_run(),
+                KeyCode::Char(' ') => {
+                    run.finish(grid);
+                    app.stop_run();
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if let Some(library) = &mut app.stamps {
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => library.select(-1),
+                KeyCode::Down | KeyCode::Char('j') => library.select(1),
+                KeyCode::Enter => app.stamps = None,
+                KeyCode::Esc | KeyCode::Char('b') => {
+                    app.stamps = None;
+                    grid.floating = None;
+                }
+                KeyCode::Delete | KeyCode::Char('x') => {
+                    let name = library.current().name.clone();
+                    app.status = Some(match library.remove_current() {
+                        Ok(true) => format!("Deleted {}", name),
+                        Ok(false) => "Only your own stamps can be deleted".to_string(),
+                        Err(err) => format!("Delete failed: {}", err),
+                    });
+                }
+                _ => {}
+            }
+            if let Some(library) = &app.stamps {
+                grid.float(library.current().cells.clone());
+            }
+            continue;
+        }
+        if let Some(command) = &mut app.command_line {
+            match key.code {
+                KeyCode::Esc => app.command_line = None,
+                KeyCode::Enter => match turtle::parse(command) {
+                    Ok(program) => {
+                        app.last_command = app.command_line.take().unwrap_or_default();
+                        app.start_run(&program);
+                    }
+                    Err(err) => app.status = Some(format!("Error at {}", err)),
+                },
+                KeyCode::Up => command.clone_from(&app.last_command),
+                KeyCode::Backspace => {
+                    command.pop();
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    command.push(c)
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if let Some(text) = &mut app.text {
+            match key.code {
+                KeyCode::Esc => {
+                    app.text = None;
+                    grid.floating = None;
+                }
+                KeyCode::Enter => {
+                    app.text = None;
+                    grid.commit_floating();
+                }
+                KeyCode::Tab => app.text_double = !app.text_double,
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => text.push(c),
+                KeyCode::Up => grid.move_cursor(0, -1),
+                KeyCode::Down => grid.move_cursor(0, 1),
+                KeyCode::Left => grid.move_cursor(-1, 0),
+                KeyCode::Right => grid.move_cursor(1, 0),
+                _ => {}
+            }
+            if app.text.is_some() {
+                app.update_text_preview();
+            }
+            continue;
+        }
+        if let Some(life) = &mut app.life {
+            match key.code {
+                KeyCode::Char(' ') => life.running = !life.running,
+                KeyCode::Char('s') | KeyCode::Right => {
+                    life.running = false;
+                    let mut cells = grid.cells().clone();
+                    life.advance(&mut cells);
+                    grid.set_cells(cells);
+                }
+                KeyCode::Char('w') => life.wrap = !life.wrap,
+                KeyCode::Char('+') => life.speed = (life.speed + 1).min(MAX_FPS),
+                KeyCode::Char('-') => life.speed = (life.speed - 1).max(1),
+                KeyCode::Char('?') => app.show_help = true,
+                KeyCode::Enter => {
+                    // keep the last generation as a single undoable edit
+                    let result = grid.cells().clone();
+                    grid.set_cells(mem::take(&mut life.snapshot));
+                    grid.checkpoint();
+                    grid.set_cells(result);
+                    grid.discard_unchanged_checkpoint();
+                    app.life = None;
+                }
+                KeyCode::Esc => {
+                    grid.set_cells(mem::take(&mut life.snapshot));
+                    app.life = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc if grid.shape.is_some() => grid.shape = None,
+            KeyCode::Esc if grid.selection.is_some() => grid.selection = None,
+            KeyCode::Esc if grid.floating.is_some() => grid.floating = None,
+            KeyCode::Esc => break,
+            KeyCode::Enter if grid.shape.is_none() && grid.floating.is_none() => {
+                if let (Some(trace), Some(target)) = (&mut app.trace, &grid.target) {
+                    trace.result = Some(trace::accuracy(grid.cells(), target));
+                }
+            }
+            KeyCode::Enter => {
+                grid.commit_shape();
+                grid.commit_floating();
+            }
+            KeyCode::Char(' ') => {
+                if key.modifiers.contains(KeyModifiers::CONTROL) {
+                    grid.set_autotoggle(!grid.autotoggle);
+                } else if grid.shape.is_some() {
+                    grid.commit_shape();
+                } else if grid.floating.is_some() {
+                    grid.commit_floating();
+                } else {
+                    grid.toggle_current();
+                }
+            }
+            KeyCode::Char('i') => grid.start_shape(Tool::Line),
+            KeyCode::Char('r') => grid.start_shape(Tool::Rect { filled: false }),
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                grid.set_autotoggle(false);
+                app.open_gallery();
+            }
+            KeyCode::Char('o') => grid.start_shape(Tool::Ellipse { filled: false }),
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.status = Some(match file::save(grid, &app.path) {
+                    Ok(()) => {
+                        if let Some(autosave) = &mut app.autosave {
+                            autosave.saved(grid);
+                        }
+                        format!("Saved {}", app.path.display())
+                    }
+                    Err(err) => format!("Save failed: {}", err),
+                });
+            }
+            KeyCode::Char(c @ ('e' | 'g' | 't'))
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                let extension = match c {
+                    'e' => "png",
+                    'g' => "gif",
+                    _ => "ans",
+                };
+                let out = app.path.with_extension(extension);
+                app.status = Some(match export::write(grid, &out, &app.export) {
+                    Ok(()) => format!("Exported {}", out.display()),
+                    Err(err) => format!("Export failed: {}", err),
+                });
+            }
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.status = (!grid.undo()).then(|| "Nothing to undo".to_string());
+            }
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.status = (!grid.redo()).then(|| "Nothing to redo".to_string());
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.status = Some(if grid.copy_selection() {
+                    "Copied".to_string()
+                } else {
+                    "Nothing selected, press V to select".to_string()
+                });
+            }
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.status = Some(if grid.cut_selection() {
+                    "Cut".to_string()
+                } else {
+                    "Nothing selected, press V to select".to_string()
+                });
+            }
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.status = (!grid.paste()).then(|| "Nothing to paste".to_string());
+            }
+            KeyCode::Char('v') => grid.start_selection(),
+            KeyCode::Char('b') => {
+                let library = Library::load(grid.color);
+                grid.float(library.current().cells.clone());
+                app.stamps = Some(library);
+            }
+            KeyCode::Char('s') => {
+                app.status = Some(match grid.selected_cells() {
+                    None => "Nothing selected, press V to select".to_string(),
+                    Some(cells) if cells.iter().flatten().all(|&c| c == palette::EMPTY) => {
+                        "Nothing drawn in the selection".to_string()
+                    }
+                    Some(cells) => match stamps::save(&cells) {
+                        Ok(name) => {
+                            grid.selection = None;
+                            format!("Saved as stamp {}", name)
+                        }
+                        Err(err) => format!("Saving the stamp failed: {}", err),
+                    },
+                });
+            }
+            KeyCode::Char('|') => grid.transform(selection::flip_horizontal),
+            KeyCode::Char('_') => grid.transform(selection::flip_vertical),
+            KeyCode::Char('t') => grid.transform(selection::rotate),
+            KeyCode::Char('y') => {
+                grid.symmetry = grid.symmetry.next();
+                app.status = Some(format!("Symmetry {}", grid.symmetry.name()));
+            }
+            KeyCode::Char('m') => {
+                app.render_mode = app.render_mode.next();
+                app.status = Some(format!("Rendering as {}", app.render_mode.name()));
+            }
+            KeyCode::Char('f') => {
+                grid.set_autotoggle(false);
+                grid.fill_current();
+            }
+            KeyCode::Char('a') => grid.add_frame(),
+            KeyCode::Char('d') => grid.duplicate_frame(),
+            KeyCode::Char('x') => grid.delete_frame(),
+            KeyCode::Char(',') | KeyCode::Char('<') => grid.step_frame(-1),
+            KeyCode::Char('.') | KeyCode::Char('>') => grid.step_frame(1),
+            KeyCode::Char('n') => {
+                grid.onion = !grid.onion;
+                let state = if grid.onion { "on" } else { "off" };
+                app.status = Some(format!("Onion skin {}", state));
+            }
+            KeyCode::Char('p') => {
+                grid.set_autotoggle(false);
+                app.playing = Some(grid.frame);
+            }
+            KeyCode::Char('w') => {
+                grid.set_autotoggle(false);
+                grid.shape = None;
+                grid.selection = None;
+                grid.floating = None;
+                app.text = Some(String::new());
+            }
+            KeyCode::Char(':') => {
+                grid.set_autotoggle(false);
+                grid.shape = None;
+                grid.selection = None;
+                grid.floating = None;
+                app.command_line = Some(String::new());
+            }
+            KeyCode::Char('g') => {
+                grid.set_autotoggle(false);
+                grid.shape = None;
+                grid.selection = None;
+                grid.floating = None;
+                app.life = Some(Life::new(grid.cells().clone()));
+            }
+            KeyCode::Char('+') => {
+                grid.fps = (grid.fps + 1).min(MAX_FPS);
+                app.status = Some(format!("{} fps", grid.fps));
+            }
+            KeyCode::Char('-') => {
+                grid.fps = (grid.fps - 1).max(1);
+                app.status = Some(format!("{} fps", grid.fps));
+            }
+            KeyCode::Char('?') => app.show_help = true,
+            KeyCode::Char(c @ '1'..='9') => grid.color = c as u8 - b'0',
+            KeyCode::Up | KeyCode::Char('k') => grid.move_cursor(0, -1),
+            KeyCode::Down | KeyCode::Char('j') => grid.move_cursor(0, 1),
+            KeyCode::Left | KeyCode::Char('h') => grid.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => grid.move_cursor(1, 0),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn ui(f: &mut Frame, app: &mut App) {
+    let size = f.size();
+    app.too_small = size.width < MIN_WIDTH || size.height < MIN_HEIGHT;
+    if app.too_small {
+        app.grid_area = Rect::default();
+        let notice = format!(
+            "Terminal too small, please make it at least {}x{}",
+            MIN_WIDTH, MIN_HEIGHT
+        );
+        let area = Rect::new(
+            0,
+            size.height.saturating_sub(3) / 2,
+            size.width,
+            size.height.min(3),
+        );
+        f.render_widget(
+            Paragraph::new(notice)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            area,
+        );
+        return;
+    }
+    if app.gallery.is_some() {
+        render_gallery(f, app, size);
+        return;
+    }
+    if let Some(puzzle) = &app.puzzle {
+        render_puzzle(f, &app.grid, puzzle, app.status.as_deref(), size);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(size);
+    let mode = if let Some(frame) = app.playing {
+        format!(
+            "Playing frame {}/{} at {} fps (+/- speed, any key to stop)",
+            frame + 1,
+            app.grid.frames.len(),
+            app.grid.fps
+        )
+    } else if let Some(life) = &app.life {
+        format!(
+            "Life generation {} at {}/s, {} edges (Space {}, S step, W edges, Enter keep, Esc back)",
+            life.generation,
+            life.speed,
+            if life.wrap { "wrapping" } else { "bounded" },
+            if life.running { "pause" } else { "run" }
+        )
+    } else if app.run.is_some() {
+        "Turtle running (Space to finish, Esc to stop)".to_string()
+    } else if let Some(command) = &app.command_line {
+        format!("Turtle: {}_ (Enter to run, Esc to cancel)", command)
+    } else if let Some(library) = &app.stamps {
+        format!(
+            "Stamp {} (Up/Down to pick, Enter to take, Esc to cancel)",
+            library.current().name
+        )
+    } else if let Some(text) = &app.text {
+        format!(
+            "Text: {}_ (Tab {} size, Enter to place, Esc to cancel)",
+            text,
+            if app.text_double { "normal" } else { "double" }
+        )
+    } else if let Some(shape) = &app.grid.shape {
+        format!("{} (Enter to draw, Esc to cancel)", shape.tool.name())
+    } else if app.grid.selection.is_some() {
+        "Selecting (Ctrl+C copy, Ctrl+X cut, S save as stamp, Esc to cancel)".to_string()
+    } else if app.grid.floating.is_some() {
+        "Pasting (Enter to place, Esc to cancel)".to_string()
+    } else if app.grid.autotoggle {
+        "Draw Mode".to_string()
+    } else {
+        "Normal Mode".to_string()
+    };
+    let mut title = mode;
+    if app.playing.is_none() && app.grid.frames.len() > 1 {
+        title += &format!(" - Frame {}/{}", app.grid.frame + 1, app.grid.frames.len());
+    }
+    if let (Some(trace), Some(target)) = (&app.trace, &app.grid.target) {
+        if app.playing.is_none() && app.life.is_none() {
+            let accuracy = trace::accuracy(app.grid.cells(), target);
+            title += &format!(" - Tracing {}: {}%, Enter when done", trace.name, accuracy);
+        }
+    }
+    match app
+        .session
+        .as_ref()
+        .map(|session| (session.is_connected(), session.port()))
+    {
+        Some((true, _)) => title += " - Drawing together",
+        Some((false, Some(port))) => title += &format!(" - Waiting for a friend on port {}", port),
+        _ => {}
+    }
+    if let Some(status) = &app.status {
+        title += &format!(" - {}", status);
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .title_bottom(Span::styled(
+            "? help  Esc quit",
+            Style::default().fg(Color::DarkGray),
+        ));
+    let mut canvas_area = chunks[0];
+    if let Some(library) = &app.stamps {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(STAMP_PANEL_WIDTH)])
+            .split(chunks[0]);
+        canvas_area = columns[0];
+        render_stamp_panel(f, library, columns[1]);
+    }
+    let inner_area = block.inner(canvas_area);
+    app.grid_area = inner_area;
+    app.view.follow(&app.grid, app.render_mode, inner_area);
+    f.render_widget(block, canvas_area);
+    let text = match app.playing {
+        Some(frame) => render::create_frame_text(&app.grid, frame, app.render_mode, app.view),
+        None => render::create_grid_text(&app.grid, app.render_mode, app.view),
+    };
+    let paragraph = Paragraph::new(text);
+    f.render_widget(paragraph, inner_area);
+    if !app.view.shows_all(&app.grid) {
+        render_minimap(f, app, inner_area);
+    }
+    f.render_widget(
+        Paragraph::new(render::create_palette_line(&app.grid)),
+        chunks[1],
+    );
+    if app.show_help {
+        render_help(f, size);
+    }
+    if app.recovered.is_some() {
+        render_recovery(f, size);
+    }
+    if let Some((trace, accuracy)) = app
+        .trace
+        .as_ref()
+        .and_then(|trace| Some((trace, trace.result?)))
+    {
+        render_trace_result(f, size, &trace.name, accuracy);
+    }
+}
+
+fn render_help(f: &mut Frame, size: Rect) {
+    let key_width = KEYS.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    let lines: Vec<Line> = KEYS
+        .iter()
+        .map(|(key, action)| {
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:<width$}  ", key, width = key_width),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(*action),
+            ])
+        })
+        .collect();
+    render_popup(f, size, "Keys", lines);
+}
+
+/// The drawings as tiles of thumbnails, the open one as it is on screen rather than saved.
+fn render_gallery(f: &mut Frame, app: &mut App, size: Rect) {
+    let Some(gallery) = &mut app.gallery else {
+        return;
+    };
+    let mut title = match &gallery.prompt {
+        Some(Prompt::Rename(name)) => {
+            format!("Rename to: {}_ (Enter to rename, Esc to cancel)", name)
+        }
+        Some(Prompt::Delete) => format!(
+            "Delete {}? (Y to delete, any other key to keep it)",
+            gallery.current().map_or("", |drawing| &drawing.name)
+        ),
+        None => "Gallery (Enter open, D duplicate, R rename, X delete, Esc back)".to_string(),
+    };
+    if let Some(status) = &app.status {
+        title += &format!(" - {}", status);
+    }
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(size);
+    f.render_widget(block, size);
+    if gallery.drawings.is_empty() {
+        let notice = "No drawings yet, press Esc to draw one and Ctrl+S to save it";
+        f.render_widget(
+            Paragraph::new(notice)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            Rect::new(inner.x, inner.y + inner.height / 2, inner.width, 2),
+        );
+        return;
+    }
+
+    let columns = (inner.width / TILE_WIDTH).max(1) as usize;
+    let rows = (inner.height / TILE_HEIGHT).max(1) as usize;
+    gallery.columns = columns;
+    // keep the selected row in view
+    let first_row = (gallery.selected / columns).saturating_sub(rows - 1);
+    let elapsed = gallery.opened.elapsed().as_millis();
+    for (i, drawing) in gallery
+        .drawings
+        .iter()
+        .enumerate()
+        .skip(first_row * columns)
+        .take(rows * columns)
+    {
+        let area = Rect::new(
+            inner.x + (i % columns) as u16 * TILE_WIDTH,
+            inner.y + (i / columns - first_row) as u16 * TILE_HEIGHT,
+            TILE_WIDTH,
+            TILE_HEIGHT,
+        )
+        .intersection(inner);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(drawing.name.clone());
+        if i == gallery.selected {
+            block = block.border_style(Style::default().fg(Color::Yellow));
+        }
+        let tile = block.inner(area);
+        f.render_widget(block, area);
+        let cells = match &drawing.grid {
+            _ if drawing.path == app.path => app.grid.cells(),
+            Ok(grid) => {
+                let frame = elapsed * grid.fps as u128 / 1000 % grid.frames.len() as u128;
+                &grid.frames[frame as usize]
+            }
+            Err(_) => {
+                f.render_widget(
+                    Paragraph::new("can't be read").wrap(Wrap { trim: true }),
+                    tile,
+                );
+                continue;
+            }
+        };
+        let thumbnail =
+            render::create_thumbnail_text(cells, tile.width as usize, tile.height as usize);
+        let (width, height) = (thumbnail.width() as u16, thumbnail.height() as u16);
+        let thumbnail_area = Rect::new(
+            tile.x + tile.width.saturating_sub(width) / 2,
+            tile.y + tile.height.saturating_sub(height) / 2,
+            width.min(tile.width),
+            height.min(tile.height),
+        );
+        f.render_widget(Paragraph::new(thumbnail), thumbnail_area);
+    }
+}
+
+/// Lists the stamps with the current one highlighted, and a preview of it at the bottom.
+fn render_stamp_panel(f: &mut Frame, library: &Library, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Stamps");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let preview = render::create_cells_text(&library.current().cells, RenderMode::HalfBlock);
+    let preview_width = (preview.width() as u16).min(inner.width);
+    let preview_height = (preview.height() as u16).min(inner.height / 2);
+    let list_height = inner.height.saturating_sub(preview_height + 1);
+    // keep the highlighted stamp in view
+    let first = library
+        .selected
+        .saturating_sub(list_height.saturating_sub(1) as usize);
+    let lines: Vec<Line> = library
+        .entries
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(list_height as usize)
+        .map(|(i, entry)| {
+            let style = if i == library.selected {
+                Style::default().fg(Color::Yellow).bg(Color::Blue)
+            } else if entry.path.is_some() {
+                // the user's own stamps
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            Line::styled(format!(" {}", entry.name), style)
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(lines),
+        Rect {
+            height: list_height,
+            ..inner
+        },
+    );
+    let preview_area = Rect::new(
+        inner.x + (inner.width - preview_width) / 2,
+        inner.bottom() - preview_height,
+        preview_width,
+        preview_height,
+    );
+    f.render_widget(Paragraph::new(preview), preview_area);
+}
+
+fn render_puzzle(f: &mut Frame, grid: &Grid, puzzle: &Puzzle, status: Option<&str>, size: Rect) {
+    let mut title = if puzzle.solved {
+        "Puzzle solved! (Esc quit)".to_string()
+    } else {
+        "Puzzle (Space fill, X mark, Esc quit)".to_string()
+    };
+    if let Some(status) = status {
+        title += &format!(" - {}", status);
+    }
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(size);
+    f.render_widget(block, size);
+
+    let text = render::create_puzzle_text(grid, puzzle);
+    let (width, height) = (text.width() as u16, text.height() as u16);
+    if width > inner.width || height > inner.height {
+        let notice = format!(
+            "The puzzle needs {}x{} characters, please make the terminal bigger",
+            width + 2,
+            height + 2
+        );
+        f.render_widget(
+            Paragraph::new(notice)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            inner,
+        );
+        return;
+    }
+    let area = Rect::new(
+        inner.x + (inner.width - width) / 2,
+        inner.y + (inner.height - height) / 2,
+        width,
+        height,
+    );
+    f.render_widget(Paragraph::new(text), area);
+}
+
+fn render_recovery(f: &mut Frame, size: Rect) {
+    let lines = vec![
+        Line::from(" An unsaved drawing from last time was found."),
+        Line::from(vec![
+            Span::raw(" Restore it? "),
+            Span::styled("Y", Style::default().fg(Color::Yellow)),
+            Span::raw(" yes  "),
+            Span::styled("N", Style::default().fg(Color::Yellow)),
+            Span::raw(" no"),
+        ]),
+    ];
+    render_popup(f, size, "Recovery", lines);
+}
+
+fn render_trace_result(f: &mut Frame, size: Rect, name: &str, accuracy: u32) {
+    let stars = trace::stars(accuracy);
+    let lines = vec![
+        Line::from(format!(" You traced {} {}% right", name, accuracy)),
+        Line::from(Span::styled(
+            format!(" {}{}", "★ ".repeat(stars), "☆ ".repeat(3 - stars)),
+            Style::default().fg(Color::Yellow),
+        )),
+        Line::from(" Press any key to keep drawing"),
+    ];
+    let title = if stars > 0 { "Well done!" } else { "Nice try!" };
+    render_popup(f, size, title, lines);
+}
+
+/// Draws `lines` in a bordered box centered on the screen.
+fn render_popup(f: &mut Frame, size: Rect, title: &str, lines: Vec<Line>) {
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 3;
+    let height = lines.len() as u16 + 2;
+    let area = Rect::new(
+        size.width.saturating_sub(width) / 2,
+        size.height.saturating_sub(height) / 2,
+        width.min(size.width),
+        height.min(size.height),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+fn render_minimap(f: &mut Frame, app: &App, inner_area: Rect) {
+    let width = app.grid.width.min(24).min(inner_area.width as usize / 3);
+    let height = app.grid.height.min(8).min(inner_area.height as usize / 3);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let mut area = Rect::new(
+        inner_area.right() - width as u16 - 2,
+        inner_area.y,
+        width as u16 + 2,
+        height as u16 + 2,
+    );
+    // move out of the way when it would hide the cursor
+    let (sx, sy) = app.render_mode.cells_per_char();
+    let cursor_row = inner_area.y + ((app.grid.cursor_y - app.view.y) / sy) as u16;
+    let cursor_column = inner_area.x + ((app.grid.cursor_x - app.view.x) / sx) as u16;
+    if cursor_column >= area.x && cursor_row < area.bottom() {
+        area.y = inner_area.bottom() - area.height;
+    }
+    let minimap = render::create_minimap_text(&app.grid, app.view, width, height);
+    let title = format!("{},{}", app.grid.cursor_x, app.grid.cursor_y);
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(minimap).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
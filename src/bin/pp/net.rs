@@ -0,0 +1,404 @@
+//! Drawing together from two terminals: one person hosts, the other joins over TCP and both
+//! edit the same drawing, each seeing the other's cursor.
+//!
+//! Messages are lines of text:
+//!
+//! ```text
+//! drawing <length>     followed by <length> bytes of a .kli file, replacing the drawing
+//! set <frame> <x> <y> <value>
+//! cursor <frame> <x> <y>
+//! ```
+//!
+//! The host decides the order of edits. It applies the guest's edits as they arrive and sends
+//! them back, so when both change a cell at about the same time, the change that reaches the
+//! host last wins on both screens. Undo only takes back one's own edits: the other person's are
+//! written into the undo history as they arrive. A whole new drawing, sent when frames are added
+//! or removed, clears the history on the receiving side.
+
+use crate::{
+    file,
+    grid::{Cells, Grid},
+    palette::PALETTE,
+};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+/// How often the editor looks for messages while nothing else happens.
+pub const POLL: Duration = Duration::from_millis(50);
+/// Largest drawing accepted, in bytes of its file, so a bad length can't exhaust memory; the
+/// size the drawing claims is checked against its rows by [`file::decode`].
+const MAX_DRAWING: usize = 64 << 20;
+
+enum Message {
+    Drawing(Box<Grid>),
+    Set {
+        frame: usize,
+        x: usize,
+        y: usize,
+        value: u8,
+    },
+    Cursor {
+        frame: usize,
+        x: usize,
+        y: usize,
+    },
+}
+
+/// What the network threads tell the editor.
+enum Incoming {
+    /// Someone connected to the host.
+    Joined(TcpStream),
+    Message(Message),
+    /// The other side closed the connection or sent something unreadable.
+    Left,
+}
+
+pub struct Session {
+    /// Port listened on, `None` for a guest.
+    port: Option<u16>,
+    incoming: Receiver<Incoming>,
+    /// Handed to reader threads of new connections.
+    incoming_sender: Sender<Incoming>,
+    /// Lines for the writer thread of the connection, if someone is connected.
+    peer: Option<Sender<Vec<u8>>>,
+    /// The frames as both sides last agreed on; local edits are the cells that differ.
+    shared: Vec<Cells>,
+    /// Last cursor position sent, as frame, x and y.
+    sent_cursor: Option<(usize, usize, usize)>,
+    /// Where the other person's cursor is, as frame, x and y.
+    peer_cursor: Option<(usize, usize, usize)>,
+}
+
+impl Session {
+    /// Listens for someone to join on `port` of every interface.
+    pub fn host(port: u16, grid: &Grid) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let (incoming_sender, incoming) = mpsc::channel();
+        let sender = incoming_sender.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if sender.send(Incoming::Joined(stream)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Session {
+            port: Some(port),
+            incoming,
+            incoming_sender,
+            peer: None,
+            shared: grid.frames.clone(),
+            sent_cursor: None,
+            peer_cursor: None,
+        })
+    }
+
+    /// Joins the host at `address` and returns the session with the host's drawing.
+    pub fn join(address: &str) -> io::Result<(Self, Grid)> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let grid = match read_message(&mut reader)? {
+            Some(Message::Drawing(grid)) => *grid,
+            _ => return Err(invalid("the host sent no drawing".to_string())),
+        };
+        let (incoming_sender, incoming) = mpsc::channel();
+        let mut session = Session {
+            port: None,
+            incoming,
+            incoming_sender,
+            peer: None,
+            shared: grid.frames.clone(),
+            sent_cursor: None,
+            peer_cursor: None,
+        };
+        session.connect(stream, reader);
+        Ok((session, grid))
+    }
+
+    /// Starts the threads reading from and writing to a connection.
+    fn connect(&mut self, stream: TcpStream, mut reader: BufReader<TcpStream>) {
+        let sender = self.incoming_sender.clone();
+        thread::spawn(move || loop {
+            match read_message(&mut reader) {
+                Ok(Some(message)) => {
+                    if sender.send(Incoming::Message(message)).is_err() {
+                        break;
+                    }
+                }
+                _ => {
+                    let _ = sender.send(Incoming::Left);
+                    break;
+                }
+            }
+        });
+        let (lines, receiver) = mpsc::channel::<Vec<u8>>();
+        let mut stream = stream;
+        thread::spawn(move || {
+            for line in receiver {
+                if stream.write_all(&line).is_err() {
+                    break;
+                }
+            }
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        });
+        self.peer = Some(lines);
+        self.sent_cursor = None;
+    }
+
+    fn send(&mut self, line: impl Into<Vec<u8>>) {
+        if let Some(peer) = &self.peer {
+            let _ = peer.send(line.into());
+        }
+    }
+
+    fn send_drawing(&mut self, grid: &Grid) {
+        let data = file::encode(grid);
+        let mut line = format!("drawing {}\n", data.len()).into_bytes();
+        line.extend(data);
+        self.send(line);
+    }
+
+    /// Whether someone is connected.
+    pub fn is_connected(&self) -> bool {
+        self.peer.is_some()
+    }
+
+    /// The port a host listens on.
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Sends local edits, applies what the other side sent and sends cursor moves. Returns news
+    /// worth telling the user about.
+    pub fn sync(&mut self, grid: &mut Grid) -> Option<String> {
+        // edits go out first so the host sees them before echoing anything over them
+        if self.peer.is_some() {
+            self.send_edits(grid);
+        }
+        let mut news = None;
+        while let Ok(incoming) = self.incoming.try_recv() {
+            match incoming {
+                Incoming::Joined(stream) if self.peer.is_none() => {
+                    let Ok(reader) = stream.try_clone().map(BufReader::new) else {
+                        continue;
+                    };
+                    let _ = stream.set_nodelay(true);
+                    self.connect(stream, reader);
+                    self.shared = grid.frames.clone();
+                    self.send_drawing(grid);
+                    news = Some("A friend joined, draw together!".to_string());
+                }
+                // only two can draw together, others are turned away by closing
+                Incoming::Joined(_) => {}
+                Incoming::Message(message) => self.apply(message, grid),
+                Incoming::Left => {
+                    self.peer = None;
+                    self.peer_cursor = None;
+                    news = Some(match self.port {
+                        Some(_) => "Your friend left".to_string(),
+                        None => "The host left, you are drawing alone".to_string(),
+                    });
+                }
+            }
+        }
+
+        if self.peer.is_some() {
+            let cursor = (grid.frame, grid.cursor_x, grid.cursor_y);
+            if self.sent_cursor != Some(cursor) {
+                self.sent_cursor = Some(cursor);
+                self.send(format!("cursor {} {} {}\n", cursor.0, cursor.1, cursor.2));
+            }
+        }
+        grid.peer_cursor = self
+            .peer_cursor
+            .filter(|&(frame, _, _)| frame == grid.frame)
+            .map(|(_, x, y)| (x, y));
+        news
+    }
+
+    fn apply(&mut self, message: Message, grid: &mut Grid) {
+        let echo = self.port.is_some();
+        match message {
+            Message::Drawing(received) => {
+                if (received.width, received.height) != (grid.width, grid.height) {
+                    return;
+                }
+                grid.set_remote_frames(received.frames);
+                self.shared = grid.frames.clone();
+                if echo {
+                    self.send_drawing(grid);
+                }
+            }
+            Message::Set { frame, x, y, value } => {
+                if frame >= grid.frames.len() || x >= grid.width || y >= grid.height {
+                    // the frames differ, a drawing message is on its way
+                    return;
+                }
+                let shared = self.shared.get_mut(frame).map(|shared| &mut shared[y][x]);
+                // the host sending back this side's own edit changes nothing, and must not make
+                // it the other person's edit that undo keeps
+                let echoed = grid.frames[frame][y][x] == value && shared.as_deref() == Some(&value);
+                if !echoed {
+                    grid.set_remote_cell(frame, x, y, value);
+                }
+                if let Some(shared) = shared {
+                    *shared = value;
+                }
+                if echo {
+                    self.send(format!("set {} {} {} {}\n", frame, x, y, value));
+                }
+            }
+            Message::Cursor { frame, x, y } => self.peer_cursor = Some((frame, x, y)),
+        }
+    }
+
+    /// Sends the cells changed here since the last sync, or the whole drawing when frames were
+    /// added or removed.
+    fn send_edits(&mut self, grid: &Grid) {
+        if grid.frames.len() != self.shared.len() {
+            self.shared = grid.frames.clone();
+            self.send_drawing(grid);
+            return;
+        }
+        let mut lines = String::new();
+        for (f, (frame, shared)) in grid.frames.iter().zip(&mut self.shared).enumerate() {
+            for (y, (row, shared_row)) in frame.iter().zip(shared.iter_mut()).enumerate() {
+                for (x, (&cell, shared_cell)) in row.iter().zip(shared_row).enumerate() {
+                    if cell != *shared_cell {
+                        *shared_cell = cell;
+                        lines += &format!("set {} {} {} {}\n", f, x, y, cell);
+                    }
+                }
+            }
+        }
+        if !lines.is_empty() {
+            self.send(lines);
+        }
+    }
+}
+
+/// The next message, `None` at the end of the connection.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Message>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let numbers: Vec<usize> = words
+        .iter()
+        .skip(1)
+        .map(|word| word.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid(format!("bad message {:?}", line.trim())))?;
+    let message = match (words.first().copied(), &numbers[..]) {
+        (Some("drawing"), &[length]) if length <= MAX_DRAWING => {
+            let mut data = vec![0; length];
+            reader.read_exact(&mut data)?;
+            Message::Drawing(Box::new(file::decode(&data)?))
+        }
+        (Some("set"), &[frame, x, y, value]) if value <= PALETTE.len() => Message::Set {
+            frame,
+            x,
+            y,
+            value: value as u8,
+        },
+        (Some("cursor"), &[frame, x, y]) => Message::Cursor { frame, x, y },
+        _ => return Err(invalid(format!("bad message {:?}", line.trim()))),
+    };
+    Ok(Some(message))
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guest(grid: &Grid) -> Session {
+        let (incoming_sender, incoming) = mpsc::channel();
+        Session {
+            port: None,
+            incoming,
+            incoming_sender,
+            peer: None,
+            shared: grid.frames.clone(),
+            sent_cursor: None,
+            peer_cursor: None,
+        }
+    }
+
+    #[test]
+    fn own_edits_sent_back_can_be_undone() {
+        let mut grid = Grid::new(3, 1);
+        let mut session = guest(&grid);
+        grid.toggle_current();
+        session.send_edits(&grid);
+        let (frame, x, y, value) = (0, 0, 0, 1);
+        session.apply(Message::Set { frame, x, y, value }, &mut grid);
+        session.apply(
+            Message::Set {
+                frame,
+                x: 2,
+                y,
+                value: 4,
+            },
+            &mut grid,
+        );
+        assert!(grid.undo());
+        assert_eq!(grid.cells()[0], [0, 0, 4]);
+        assert!(!grid.undo());
+    }
+
+    fn read(data: &[u8]) -> io::Result<Option<Message>> {
+        read_message(&mut BufReader::new(data))
+    }
+
+    #[test]
+    fn reads_messages() {
+        assert!(matches!(
+            read(b"set 1 2 3 9\n"),
+            Ok(Some(Message::Set {
+                frame: 1,
+                x: 2,
+                y: 3,
+                value: 9
+            }))
+        ));
+        assert!(matches!(
+            read(b"cursor 0 4 5\n"),
+            Ok(Some(Message::Cursor {
+                frame: 0,
+                x: 4,
+                y: 5
+            }))
+        ));
+        assert!(matches!(read(b""), Ok(None)));
+        let data = file::encode(&Grid::new(2, 2));
+        let mut message = format!("drawing {}\n", data.len()).into_bytes();
+        message.extend(data);
+        assert!(matches!(read(&message), Ok(Some(Message::Drawing(_)))));
+    }
+
+    #[test]
+    fn refuses_bad_messages() {
+        assert!(read(b"set 0 0 0 10\n").is_err());
+        assert!(read(b"set 0 0 0 255\n").is_err());
+        assert!(read(b"set 0 0 -1 1\n").is_err());
+        assert!(read(b"cursor 0 0\n").is_err());
+        assert!(read(b"jump 1\n").is_err());
+        assert!(read(b"drawing 999999999999\n").is_err());
+        // a small message claiming a huge drawing
+        let data = b"kli-pp 3\n4000000000 4000000000\n0 0\n1 8\n";
+        let mut message = format!("drawing {}\n", data.len()).into_bytes();
+        message.extend(data);
+        assert!(read(&message).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Nonogram puzzles made from drawings: the clues give the runs of filled cells in every row
+//! and column, and the drawing is revealed once the player has filled exactly its cells.
+
+use crate::{grid::Cells, palette::EMPTY};
+use std::mem;
+
+/// Guesses the solver may try before giving up on proving a puzzle has a single solution.
+const GUESS_BUDGET: usize = 2_000;
+
+pub struct Puzzle {
+    pub solution: Cells,
+    pub row_clues: Vec<Vec<usize>>,
+    pub column_clues: Vec<Vec<usize>>,
+    /// Cells the player marked as certainly empty.
+    pub marks: Vec<Vec<bool>>,
+    pub solved: bool,
+}
+
+impl Puzzle {
+    /// A puzzle whose picture is the drawn part of `cells`, without the empty rows and columns
+    /// around it, if the clues lead to it and to nothing else.
+    pub fn new(cells: Cells) -> Result<Self, String> {
+        let solution = crop_to_drawn(&cells).ok_or_else(|| "nothing is drawn".to_string())?;
+        let (width, height) = (solution[0].len(), solution.len());
+        let row_clues: Vec<_> = solution.iter().map(|row| clues(row)).collect();
+        let column_clues: Vec<_> = (0..width).map(|x| clues(&column(&solution, x))).collect();
+        let mut budget = GUESS_BUDGET;
+        let mut board = vec![vec![Cell::Unknown; width]; height];
+        match count_solutions(&row_clues, &column_clues, &mut board, &mut budget) {
+            Some(1) => Ok(Puzzle {
+                marks: vec![vec![false; width]; height],
+                solution,
+                row_clues,
+                column_clues,
+                solved: false,
+            }),
+            Some(_) => Err("its clues fit more than one picture".to_string()),
+            None => Err("it is too hard to check that its clues fit only one picture".to_string()),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.solution[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.solution.len()
+    }
+
+    /// Whether `cells` has exactly the filled cells of the solution, whatever their colors.
+    pub fn is_solved_by(&self, cells: &Cells) -> bool {
+        cells.iter().zip(&self.solution).all(|(row, solution)| {
+            row.iter()
+                .zip(solution)
+                .all(|(&a, &b)| (a == EMPTY) == (b == EMPTY))
+        })
+    }
+}
+
+/// The smallest rectangle of `cells` holding all filled cells, `None` if there are none.
+fn crop_to_drawn(cells: &Cells) -> Option<Cells> {
+    let filled = |row: &Vec<u8>| row.iter().any(|&c| c != EMPTY);
+    let top = cells.iter().position(filled)?;
+    let bottom = cells.iter().rposition(filled)?;
+    let rows = &cells[top..=bottom];
+    let left = rows
+        .iter()
+        .filter_map(|row| row.iter().position(|&c| c != EMPTY))
+        .min()?;
+    let right = rows
+        .iter()
+        .filter_map(|row| row.iter().rposition(|&c| c != EMPTY))
+        .max()?;
+    Some(rows.iter().map(|row| row[left..=right].to_vec()).collect())
+}
+
+/// Lengths of the runs of filled cells in a line.
+pub fn clues(line: &[u8]) -> Vec<usize> {
+    line.split(|&c| c == EMPTY)
+        .map(<[u8]>::len)
+        .filter(|&len| len > 0)
+        .collect()
+}
+
+fn column(cells: &Cells, x: usize) -> Vec<u8> {
+    cells.iter().map(|row| row[x]).collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+/// Counts solutions up to 2, filling in `board`; `None` when the guess budget runs out first.
+fn count_solutions(
+    rows: &[Vec<usize>],
+    columns: &[Vec<usize>],
+    board: &mut [Vec<Cell>],
+    budget: &mut usize,
+) -> Option<usize> {
+    if !propagate(rows, columns, board) {
+        return Some(0);
+    }
+    let unknown = board
+        .iter()
+        .enumerate()
+        .find_map(|(y, row)| row.iter().position(|&c| c == Cell::Unknown).map(|x| (x, y)));
+    let Some((x, y)) = unknown else {
+        return Some(1);
+    };
+    let mut count = 0;
+    for guess in [Cell::Filled, Cell::Empty] {
+        *budget = budget.checked_sub(1)?;
+        let mut attempt = board.to_vec();
+        attempt[y][x] = guess;
+        count += count_solutions(rows, columns, &mut attempt, budget)?;
+        if count > 1 {
+            break;
+        }
+    }
+    Some(count)
+}
+
+/// Solves single lines over and over until nothing changes; false on a contradiction. Only
+/// lines crossing a cell that changed are solved again.
+fn propagate(rows: &[Vec<usize>], columns: &[Vec<usize>], board: &mut [Vec<Cell>]) -> bool {
+    let (width, height) = (columns.len(), rows.len());
+    let mut dirty_rows = vec![true; height];
+    let mut dirty_columns = vec![true; width];
+    while dirty_rows.contains(&true) || dirty_columns.contains(&true) {
+        for y in 0..height {
+            if !mem::take(&mut dirty_rows[y]) {
+                continue;
+            }
+            let Some(line) = solve_line(&rows[y], &board[y]) else {
+                return false;
+            };
+            for x in 0..width {
+                if line[x] != board[y][x] {
+                    dirty_columns[x] = true;
+                }
+            }
+            board[y] = line;
+        }
+        for x in 0..width {
+            if !mem::take(&mut dirty_columns[x]) {
+                continue;
+            }
+            let current: Vec<Cell> = (0..height).map(|y| board[y][x]).collect();
+            let Some(line) = solve_line(&columns[x], &current) else {
+                return false;
+            };
+            for (y, cell) in line.into_iter().enumerate() {
+                if cell != board[y][x] {
+                    dirty_rows[y] = true;
+                    board[y][x] = cell;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Settles every cell of a line that is the same in all placements of the clues agreeing with
+/// the known cells, `None` when there is no such placement.
+fn solve_line(clues: &[usize], line: &[Cell]) -> Option<Vec<Cell>> {
+    let (n, k) = (line.len(), clues.len());
+    // whether the block starting at `i` can have length `len`, followed by an empty cell or
+    // the end of the line
+    let block_fits = |i: usize, len: usize| {
+        i + len <= n
+            && line[i..i + len].iter().all(|&c| c != Cell::Empty)
+            && line.get(i + len) != Some(&Cell::Filled)
+    };
+    let after_block = |i: usize, len: usize| (i + len + 1).min(n);
+
+    // fits[i][j]: cells from i on can hold clues from j on
+    let mut fits = vec![vec![false; k + 1]; n + 1];
+    fits[n][k] = true;
+    for i in (0..n).rev() {
+        for j in 0..=k {
+            fits[i][j] = (line[i] != Cell::Filled && fits[i + 1][j])
+                || (j < k && block_fits(i, clues[j]) && fits[after_block(i, clues[j])][j + 1]);
+        }
+    }
+    if !fits[0][0] {
+        return None;
+    }
+
+    // walk the placements forward, noting what every cell can be
+    let mut reached = vec![vec![false; k + 1]; n + 1];
+    reached[0][0] = true;
+    let (mut can_fill, mut can_empty) = (vec![false; n], vec![false; n]);
+    for i in 0..n {
+        for j in 0..=k {
+            if !reached[i][j] || !fits[i][j] {
+                continue;
+            }
+            if line[i] != Cell::Filled && fits[i + 1][j] {
+                can_empty[i] = true;
+                reached[i + 1][j] = true;
+            }
+            if j < k && block_fits(i, clues[j]) && fits[after_block(i, clues[j])][j + 1] {
+                let len = clues[j];
+                can_fill[i..i + len].fill(true);
+                if i + len < n {
+                    can_empty[i + len] = true;
+                }
+                reached[after_block(i, len)][j + 1] = true;
+            }
+        }
+    }
+    Some(
+        (0..n)
+            .map(|i| match (can_fill[i], can_empty[i]) {
+                (true, false) => Cell::Filled,
+                (false, true) => Cell::Empty,
+                _ => Cell::Unknown,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rows: &[&str]) -> Cells {
+        rows.iter()
+            .map(|row| row.bytes().map(|b| (b == b'1') as u8).collect())
+            .collect()
+    }
+
+    #[test]
+    fn clues_count_runs() {
+        assert_eq!(clues(&[1, 1, 0, 3, 0, 0, 2]), [2, 1, 1]);
+        assert!(clues(&[0, 0]).is_empty());
+    }
+
+    #[test]
+    fn solves_single_lines() {
+        use Cell::*;
+        // a block of 3 in 4 cells always covers the middle two
+        let line = solve_line(&[3], &[Unknown; 4]).unwrap();
+        assert!(line == [Unknown, Filled, Filled, Unknown]);
+        let line = solve_line(&[1, 1], &[Unknown; 4]).unwrap();
+        assert!(line.iter().all(|&c| c == Unknown));
+        let line = solve_line(&[1, 1], &[Unknown, Unknown, Unknown, Empty]).unwrap();
+        assert!(line == [Filled, Empty, Filled, Empty]);
+        assert!(solve_line(&[2], &[Filled, Empty, Filled]).is_none());
+    }
+
+    #[test]
+    fn accepts_a_picture_with_one_solution() {
+        let picture = cells(&[".1.", "111", ".1.", "1.1"]);
+        let puzzle = Puzzle::new(picture.clone()).ok().unwrap();
+        assert_eq!(puzzle.row_clues, [vec![1], vec![3], vec![1], vec![1, 1]]);
+        assert!(puzzle.is_solved_by(&picture));
+        assert!(!puzzle.is_solved_by(&cells(&[".1.", "111", ".1.", "11."])));
+    }
+
+    #[test]
+    fn crops_to_the_drawn_part() {
+        let puzzle = Puzzle::new(cells(&["....", "..1.", ".11.", "....", "...."]))
+            .ok()
+            .unwrap();
+        assert_eq!((puzzle.width(), puzzle.height()), (2, 2));
+        assert_eq!(puzzle.solution, cells(&[".1", "11"]));
+    }
+
+    #[test]
+    fn refuses_a_picture_with_two_solutions() {
+        // the other diagonal has the same clues
+        let err = Puzzle::new(cells(&["1.", ".1"])).err().unwrap();
+        assert_eq!(err, "its clues fit more than one picture");
+        assert!(Puzzle::new(cells(&["..", ".."])).is_err());
+    }
+
+    #[test]
+    fn gives_up_when_out_of_guesses() {
+        let (rows, columns) = (vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+        let mut board = vec![vec![Cell::Unknown; 2]; 2];
+        assert_eq!(count_solutions(&rows, &columns, &mut board, &mut 0), None);
+        let mut board = vec![vec![Cell::Unknown; 2]; 2];
+        assert_eq!(
+            count_solutions(&rows, &columns, &mut board, &mut 10),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use kli::colors::{BORDER_COLORS, SPECIAL_COLORS};
+use ratatui::style::Color;
+
+/// Cell value of an empty cell, every other value is a 1-based palette index.
+pub const EMPTY: u8 = 0;
+
+/// The colors picked with keys 1-9: the ones of `move` first, then white and black.
+pub const PALETTE: [Color; 9] = [
+    BORDER_COLORS[0],
+    BORDER_COLORS[1],
+    BORDER_COLORS[2],
+    BORDER_COLORS[3],
+    SPECIAL_COLORS[0],
+    SPECIAL_COLORS[1],
+    SPECIAL_COLORS[2],
+    Color::White,
+    Color::Black,
+];
+
+pub fn color(cell: u8) -> Option<Color> {
+    match cell {
+        EMPTY => None,
+        _ => PALETTE.get(cell as usize - 1).copied(),
+    }
+}
+
+/// Cells written as rows of palette indexes, `.` or any other non-digit for empty cells, as
+/// the built-in pictures are.
+pub fn parse_rows(rows: &[&str]) -> Vec<Vec<u8>> {
+    rows.iter()
+        .map(|row| {
+            row.bytes()
+                .map(|b| if b.is_ascii_digit() { b - b'0' } else { EMPTY })
+                .collect()
+        })
+        .collect()
+}
+
+/// RGB value used when a cell has to leave the terminal, e.g. in exported images.
+pub fn rgb(cell: u8) -> [u8; 3] {
+    match color(cell) {
+        None => [255, 255, 255],
+        Some(Color::Rgb(r, g, b)) => [r, g, b],
+        Some(Color::Red) => [205, 0, 0],
+        Some(Color::Blue) => [0, 0, 238],
+        Some(Color::Green) => [0, 205, 0],
+        Some(Color::Yellow) => [205, 205, 0],
+        Some(Color::Cyan) => [0, 205, 205],
+        Some(Color::White) => [229, 229, 229],
+        Some(_) => [0, 0, 0],
+    }
+}
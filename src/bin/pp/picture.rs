@@ -0,0 +1,368 @@
+//! Turning image files into drawings: PNG and the netpbm formats (PBM, PGM, PPM) are read,
+//! fitted into the canvas and reduced to palette colors.
+
+use crate::{
+    grid::{Cells, Grid},
+    palette::{self, EMPTY, PALETTE},
+};
+use clap::ValueEnum;
+use std::{fs, io, path::Path};
+
+/// Palette index of the ink used by the black and white conversions.
+const INK: u8 = PALETTE.len() as u8;
+/// Most pixels read from a netpbm image, whose header could claim any size.
+const MAX_PIXELS: usize = 1 << 26;
+
+/// How pixels become cells.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Conversion {
+    /// Black where darker than the threshold, empty elsewhere
+    Threshold,
+    /// Black and empty, spreading the rounding error to neighbouring cells
+    FloydSteinberg,
+    /// Black and empty, with a regular 4x4 pattern for grays
+    Ordered,
+    /// The nearest palette color, light pixels staying empty
+    Palette,
+}
+
+/// An RGB picture, alpha already blended onto white.
+pub struct Picture {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+/// Reads a PNG, or a netpbm image when the file starts with a `P1`-`P6` magic.
+pub fn load(path: &Path) -> io::Result<Picture> {
+    let data = fs::read(path)?;
+    match data.get(..2) {
+        Some([b'P', b'1'..=b'6']) => parse_pnm(&data),
+        _ => decode_png(&data),
+    }
+}
+
+fn decode_png(data: &[u8]) -> io::Result<Picture> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| invalid(format!("not a PNG or netpbm image ({})", err)))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|err| invalid(err.to_string()))?;
+    let channels = info.color_type.samples();
+    let pixels = buf[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|px| {
+            let (rgb, alpha) = match px {
+                [v] => ([*v; 3], 255),
+                [v, a] => ([*v; 3], *a),
+                [r, g, b] => ([*r, *g, *b], 255),
+                [r, g, b, a, ..] => ([*r, *g, *b], *a),
+                [] => ([255; 3], 0),
+            };
+            rgb.map(|c| blend_on_white(c, alpha))
+        })
+        .collect();
+    Ok(Picture {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+fn blend_on_white(value: u8, alpha: u8) -> u8 {
+    ((value as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255) as u8
+}
+
+/// Reads any of the plain (`P1`-`P3`) and raw (`P4`-`P6`) netpbm formats.
+fn parse_pnm(data: &[u8]) -> io::Result<Picture> {
+    let kind = data[1] - b'0';
+    let mut pos = 2;
+    let header = |pos: &mut usize| -> io::Result<usize> {
+        let token = next_token(data, pos).ok_or_else(|| invalid("truncated header".into()))?;
+        token
+            .parse()
+            .map_err(|_| invalid(format!("bad header value {:?}", token)))
+    };
+    let width = header(&mut pos)?;
+    let height = header(&mut pos)?;
+    let maxval = if matches!(kind, 1 | 4) {
+        1
+    } else {
+        header(&mut pos)?
+    };
+    if width == 0 || height == 0 || maxval == 0 || maxval > u16::MAX as usize {
+        return Err(invalid(format!(
+            "bad header: {}x{} with maximum value {}",
+            width, height, maxval
+        )));
+    }
+    let count = width
+        .checked_mul(height)
+        .filter(|&count| count <= MAX_PIXELS)
+        .ok_or_else(|| invalid(format!("{}x{} is too large", width, height)))?;
+
+    let samples: Vec<usize> = match kind {
+        1 => {
+            // plain bitmaps may run digits together, every 0 or 1 is a pixel
+            let bits = data[pos..]
+                .iter()
+                .filter(|b| matches!(b, b'0' | b'1'))
+                .map(|&b| (b - b'0') as usize);
+            bits.take(count).collect()
+        }
+        2 | 3 => {
+            let mut samples = Vec::new();
+            while let Some(token) = next_token(data, &mut pos) {
+                samples.push(
+                    token
+                        .parse()
+                        .map_err(|_| invalid(format!("bad sample {:?}", token)))?,
+                );
+            }
+            samples
+        }
+        4 => {
+            // a single whitespace byte separates the header from the data
+            let body = data.get(pos + 1..).unwrap_or_default();
+            let row_bytes = width.div_ceil(8);
+            if body.len() < row_bytes * height {
+                return Err(invalid(format!(
+                    "expected {} bytes of pixels, found {}",
+                    row_bytes * height,
+                    body.len()
+                )));
+            }
+            (0..count)
+                .filter_map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    let byte = body.get(y * row_bytes + x / 8)?;
+                    Some(((byte >> (7 - x % 8)) & 1) as usize)
+                })
+                .collect()
+        }
+        _ => {
+            let body = data.get(pos + 1..).unwrap_or_default();
+            if maxval < 256 {
+                body.iter().map(|&b| b as usize).collect()
+            } else {
+                body.chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as usize)
+                    .collect()
+            }
+        }
+    };
+
+    let channels = if matches!(kind, 3 | 6) { 3 } else { 1 };
+    if samples.len() < count * channels {
+        return Err(invalid(format!(
+            "expected {} samples, found {}",
+            count * channels,
+            samples.len()
+        )));
+    }
+    let to_byte = |sample: usize| (sample.min(maxval) * 255 / maxval) as u8;
+    let pixels = (0..count)
+        .map(|i| match kind {
+            // in bitmaps 1 is black
+            1 | 4 => [if samples[i] == 1 { 0 } else { 255 }; 3],
+            2 | 5 => [to_byte(samples[i]); 3],
+            _ => [0, 1, 2].map(|c| to_byte(samples[i * 3 + c])),
+        })
+        .collect();
+    Ok(Picture {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// The next whitespace-separated header token, skipping `#` comments up to the end of line.
+fn next_token<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a str> {
+    loop {
+        match data.get(*pos)? {
+            b'#' => {
+                while data.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while data
+        .get(*pos)
+        .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+    {
+        *pos += 1;
+    }
+    std::str::from_utf8(&data[start..*pos]).ok()
+}
+
+/// Makes a `width`x`height` drawing of the picture, scaled to fit and centered.
+pub fn to_grid(
+    picture: &Picture,
+    width: usize,
+    height: usize,
+    conversion: Conversion,
+    threshold: u8,
+) -> Grid {
+    // keep the aspect ratio, cells are treated as square like in exports
+    let scale = f64::min(
+        width as f64 / picture.width as f64,
+        height as f64 / picture.height as f64,
+    );
+    let fit_width = ((picture.width as f64 * scale).round() as usize).clamp(1, width);
+    let fit_height = ((picture.height as f64 * scale).round() as usize).clamp(1, height);
+    let resized = resize(picture, fit_width, fit_height);
+    let cells = convert(&resized, conversion, threshold);
+
+    let mut grid = Grid::new(width, height);
+    let (left, top) = ((width - fit_width) / 2, (height - fit_height) / 2);
+    let mut canvas = grid.cells().clone();
+    for (row, cells_row) in canvas[top..].iter_mut().zip(&cells) {
+        row[left..left + fit_width].copy_from_slice(cells_row);
+    }
+    grid.set_cells(canvas);
+    grid
+}
+
+/// Averages the block of pixels under every target pixel, or repeats pixels when enlarging.
+fn resize(picture: &Picture, width: usize, height: usize) -> Vec<Vec<[f64; 3]>> {
+    let span = |i: usize, n: usize, total: usize| {
+        let start = i * total / n;
+        start..((i + 1) * total / n).max(start + 1)
+    };
+    (0..height)
+        .map(|y| {
+            let ys = span(y, height, picture.height);
+            (0..width)
+                .map(|x| {
+                    let xs = span(x, width, picture.width);
+                    let mut sum = [0.0; 3];
+                    for py in ys.clone() {
+                        for px in xs.clone() {
+                            let pixel = picture.pixels[py * picture.width + px];
+                            for c in 0..3 {
+                                sum[c] += pixel[c] as f64;
+                            }
+                        }
+                    }
+                    let n = (ys.len() * xs.len()) as f64;
+                    sum.map(|c| c / n)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn convert(pixels: &[Vec<[f64; 3]>], conversion: Conversion, threshold: u8) -> Cells {
+    let ink = |dark: bool| if dark { INK } else { EMPTY };
+    let mut lum: Vec<Vec<f64>> = pixels
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|[r, g, b]| 0.299 * r + 0.587 * g + 0.114 * b)
+                .collect()
+        })
+        .collect();
+    match conversion {
+        Conversion::Threshold => lum
+            .iter()
+            .map(|row| row.iter().map(|&l| ink(l < threshold as f64)).collect())
+            .collect(),
+        Conversion::Ordered => {
+            const BAYER: [[f64; 4]; 4] = [
+                [0.0, 8.0, 2.0, 10.0],
+                [12.0, 4.0, 14.0, 6.0],
+                [3.0, 11.0, 1.0, 9.0],
+                [15.0, 7.0, 13.0, 5.0],
+            ];
+            lum.iter()
+                .enumerate()
+                .map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(|(x, &l)| ink(l < (BAYER[y % 4][x % 4] + 0.5) * 16.0))
+                        .collect()
+                })
+                .collect()
+        }
+        Conversion::FloydSteinberg => {
+            let (height, width) = (lum.len(), lum.first().map_or(0, Vec::len));
+            let mut cells = vec![vec![EMPTY; width]; height];
+            for y in 0..height {
+                for x in 0..width {
+                    let old = lum[y][x];
+                    let dark = old < 128.0;
+                    cells[y][x] = ink(dark);
+                    let error = old - if dark { 0.0 } else { 255.0 };
+                    let mut spread = |x: Option<usize>, y: usize, weight: f64| {
+                        if let Some(px) = x.and_then(|x| lum.get_mut(y)?.get_mut(x)) {
+                            *px += error * weight / 16.0;
+                        }
+                    };
+                    spread(Some(x + 1), y, 7.0);
+                    spread(x.checked_sub(1), y + 1, 3.0);
+                    spread(Some(x), y + 1, 5.0);
+                    spread(Some(x + 1), y + 1, 1.0);
+                }
+            }
+            cells
+        }
+        Conversion::Palette => pixels
+            .iter()
+            .map(|row| row.iter().map(|&pixel| nearest(pixel)).collect())
+            .collect(),
+    }
+}
+
+/// The cell whose export color is closest to `pixel`, empty cells counting as white.
+fn nearest(pixel: [f64; 3]) -> u8 {
+    (EMPTY..=PALETTE.len() as u8)
+        .min_by(|&a, &b| distance(pixel, a).total_cmp(&distance(pixel, b)))
+        .unwrap_or(EMPTY)
+}
+
+fn distance(pixel: [f64; 3], cell: u8) -> f64 {
+    let rgb = palette::rgb(cell);
+    (0..3).map(|c| (pixel[c] - rgb[c] as f64).powi(2)).sum()
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_plain_and_raw_bitmaps() {
+        let plain = parse_pnm(b"P1\n3 2\n# comment\n101\n010\n").unwrap();
+        let raw = parse_pnm(b"P4\n3 2\n\xa0\x40").unwrap();
+        for picture in [plain, raw] {
+            assert_eq!((picture.width, picture.height), (3, 2));
+            let black: Vec<bool> = picture.pixels.iter().map(|p| p[0] == 0).collect();
+            assert_eq!(black, [true, false, true, false, true, false]);
+        }
+    }
+
+    #[test]
+    fn scales_gray_samples() {
+        let picture = parse_pnm(b"P2 2 1 4 0 4").unwrap();
+        assert_eq!(picture.pixels, [[0; 3], [255; 3]]);
+    }
+
+    #[test]
+    fn refuses_huge_or_truncated_images() {
+        assert!(parse_pnm(b"P1 99999999999 99999999999 1").is_err());
+        assert!(parse_pnm(b"P4\n100000 100000\n\xff").is_err());
+        assert!(parse_pnm(b"P4\n16 2\n\xff\xff\xff").is_err());
+        assert!(parse_pnm(b"P3 2 1 255 0 0 0").is_err());
+    }
+}
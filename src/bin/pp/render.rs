@@ -0,0 +1,574 @@
+use crate::{
+    grid::{Cells, Grid},
+    nonogram::{self, Puzzle},
+    palette,
+    selection::Area,
+};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use std::ops::Range;
+
+/// Color of empty cells where the mode can't draw a shade character.
+const EMPTY_COLOR: Color = Color::DarkGray;
+/// Color of the previous frame's cells where the mode can't dim them.
+const ONION_COLOR: Color = Color::Gray;
+/// Color of the cursor cell where the mode can't highlight a whole character.
+const CURSOR_COLOR: Color = Color::LightMagenta;
+/// Background of selected cells, or color of their empty parts where the mode can't tint them.
+const SELECTION_COLOR: Color = Color::DarkGray;
+const SELECTED_EMPTY_COLOR: Color = Color::Blue;
+/// Color of the traced picture where the mode can't dim its colors.
+const TARGET_COLOR: Color = Color::Gray;
+/// Color of the symmetry axes over empty cells.
+const GUIDE_COLOR: Color = Color::Cyan;
+/// Background of the other person's cursor while drawing together, or its color where the mode
+/// can't highlight a whole character.
+const PEER_COLOR: Color = Color::LightRed;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    /// One cell per character.
+    Cells,
+    /// Two cells per character with `▀`, so cells are about square.
+    HalfBlock,
+    /// 2x4 cells per character as braille dots.
+    Braille,
+}
+
+impl RenderMode {
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Cells => RenderMode::HalfBlock,
+            RenderMode::HalfBlock => RenderMode::Braille,
+            RenderMode::Braille => RenderMode::Cells,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Cells => "cells",
+            RenderMode::HalfBlock => "half-block",
+            RenderMode::Braille => "braille",
+        }
+    }
+
+    /// Number of cells covered by one character, horizontally and vertically.
+    pub fn cells_per_char(self) -> (usize, usize) {
+        match self {
+            RenderMode::Cells => (1, 1),
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Braille => (2, 4),
+        }
+    }
+}
+
+/// The part of the canvas that is on screen, in cells.
+#[derive(Clone, Copy, Default)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    /// Resizes the viewport to `area` and scrolls it just enough to keep the cursor visible.
+    pub fn follow(&mut self, grid: &Grid, mode: RenderMode, area: Rect) {
+        let (sx, sy) = mode.cells_per_char();
+        self.width = (area.width as usize * sx).min(grid.width);
+        self.height = (area.height as usize * sy).min(grid.height);
+        self.x = scroll(self.x, self.width, grid.cursor_x, grid.width);
+        self.y = scroll(self.y, self.height, grid.cursor_y, grid.height);
+    }
+
+    /// Whether the whole canvas is on screen.
+    pub fn shows_all(&self, grid: &Grid) -> bool {
+        self.width == grid.width && self.height == grid.height
+    }
+}
+
+fn scroll(offset: usize, visible: usize, cursor: usize, total: usize) -> usize {
+    let offset = if cursor < offset {
+        cursor
+    } else if cursor >= offset + visible {
+        cursor + 1 - visible
+    } else {
+        offset
+    };
+    offset.min(total - visible)
+}
+
+/// What is drawn on top of the cells, in the coordinates of the cropped cells.
+#[derive(Default)]
+struct Overlay {
+    onion: Option<Cells>,
+    target: Option<Cells>,
+    cursor: Option<(usize, usize)>,
+    peer: Option<(usize, usize)>,
+    highlight: Option<Area>,
+    guide_columns: Range<usize>,
+    guide_rows: Range<usize>,
+}
+
+impl Overlay {
+    fn onion_at(&self, x: usize, y: usize) -> u8 {
+        self.onion
+            .as_ref()
+            .map_or(palette::EMPTY, |onion| onion[y][x])
+    }
+
+    fn target_at(&self, x: usize, y: usize) -> u8 {
+        self.target
+            .as_ref()
+            .map_or(palette::EMPTY, |target| target[y][x])
+    }
+
+    fn is_cursor(&self, x: usize, y: usize) -> bool {
+        self.cursor == Some((x, y))
+    }
+
+    fn is_peer(&self, x: usize, y: usize) -> bool {
+        self.peer == Some((x, y))
+    }
+
+    fn is_highlighted(&self, x: usize, y: usize) -> bool {
+        self.highlight.is_some_and(|area| area.contains(x, y))
+    }
+
+    fn is_guide(&self, x: usize, y: usize) -> bool {
+        self.guide_columns.contains(&x) || self.guide_rows.contains(&y)
+    }
+}
+
+pub fn create_grid_text(grid: &Grid, mode: RenderMode, view: Viewport) -> Text<'static> {
+    let cells = crop(&grid.display_cells(), view);
+    let overlay = Overlay {
+        onion: grid.onion_cells().map(|onion| crop(onion, view)),
+        target: grid.target.as_ref().map(|target| crop(target, view)),
+        cursor: Some((
+            grid.cursor_x.saturating_sub(view.x),
+            grid.cursor_y.saturating_sub(view.y),
+        )),
+        peer: grid
+            .peer_cursor
+            .filter(|&(x, y)| view.x <= x && view.y <= y)
+            .map(|(x, y)| (x - view.x, y - view.y)),
+        highlight: grid
+            .highlight()
+            .filter(|area| {
+                area.x1 >= view.x
+                    && area.x0 < view.x + view.width
+                    && area.y1 >= view.y
+                    && area.y0 < view.y + view.height
+            })
+            .map(|area| Area {
+                x0: area.x0.saturating_sub(view.x),
+                y0: area.y0.saturating_sub(view.y),
+                x1: area.x1 - view.x,
+                y1: area.y1 - view.y,
+            }),
+        guide_columns: shift(grid.symmetry.guide_columns(grid.width), view.x),
+        guide_rows: shift(grid.symmetry.guide_rows(grid.height), view.y),
+    };
+    create_text(&cells, &overlay, mode)
+}
+
+/// Renders one animation frame alone, as shown during playback.
+pub fn create_frame_text(
+    grid: &Grid,
+    frame: usize,
+    mode: RenderMode,
+    view: Viewport,
+) -> Text<'static> {
+    create_text(&crop(&grid.frames[frame], view), &Overlay::default(), mode)
+}
+
+/// Renders cells on their own, without cursor or anything else over them.
+pub fn create_cells_text(cells: &Cells, mode: RenderMode) -> Text<'static> {
+    create_text(cells, &Overlay::default(), mode)
+}
+
+/// The cells shrunk to fit in `width`x`height` characters of half blocks, every pixel taking
+/// the most used color of the cells it covers so thin lines don't vanish.
+pub fn create_thumbnail_text(cells: &Cells, width: usize, height: usize) -> Text<'static> {
+    let (cells_width, cells_height) = (cells.first().map_or(0, Vec::len), cells.len());
+    let scale = cells_width
+        .div_ceil(width)
+        .max(cells_height.div_ceil(height * 2))
+        .max(1);
+    let pixel = |px: usize, py: usize| {
+        let mut counts = [0usize; palette::PALETTE.len() + 1];
+        for row in cells.iter().skip(py * scale).take(scale) {
+            for &cell in row.iter().skip(px * scale).take(scale) {
+                counts[cell as usize] += 1;
+            }
+        }
+        (1..counts.len())
+            .filter(|&i| counts[i] > 0)
+            .max_by_key(|&i| (counts[i], std::cmp::Reverse(i)))
+            .map_or(palette::EMPTY, |i| i as u8)
+    };
+    let thumbnail: Cells = (0..cells_height.div_ceil(scale))
+        .map(|py| {
+            (0..cells_width.div_ceil(scale))
+                .map(|px| pixel(px, py))
+                .collect()
+        })
+        .collect();
+    Text::from(half_block_lines(&thumbnail, &Overlay::default()))
+}
+
+fn shift(range: Range<usize>, offset: usize) -> Range<usize> {
+    range.start.saturating_sub(offset)..range.end.saturating_sub(offset)
+}
+
+fn crop(cells: &Cells, view: Viewport) -> Cells {
+    cells
+        .iter()
+        .skip(view.y)
+        .take(view.height)
+        .map(|row| row[view.x..view.x + view.width].to_vec())
+        .collect()
+}
+
+fn create_text(cells: &Cells, overlay: &Overlay, mode: RenderMode) -> Text<'static> {
+    let lines = match mode {
+        RenderMode::Cells => cells_lines(cells, overlay),
+        RenderMode::HalfBlock => half_block_lines(cells, overlay),
+        RenderMode::Braille => braille_lines(cells, overlay),
+    };
+    Text::from(lines)
+}
+
+fn cells_lines(cells: &Cells, overlay: &Overlay) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for (y, row) in cells.iter().enumerate() {
+        let mut spans = Vec::new();
+        for (x, &cell) in row.iter().enumerate() {
+            let color = palette::color(cell);
+            let highlighted = overlay.is_highlighted(x, y);
+            let target = palette::color(overlay.target_at(x, y));
+            // a full block would hide the highlight behind it
+            let ch = match (color, highlighted, target) {
+                (Some(_), false, _) => '█',
+                (Some(_), true, _) => '▓',
+                (None, _, Some(_)) => '▒',
+                (None, _, None) => '░',
+            };
+            let mut style = if overlay.is_cursor(x, y) {
+                Style::default().fg(Color::Yellow).bg(Color::Blue)
+            } else if overlay.is_peer(x, y) {
+                Style::default()
+                    .fg(color.unwrap_or(Color::White))
+                    .bg(PEER_COLOR)
+            } else if let Some(color) = color {
+                Style::default().fg(color)
+            } else if let Some(target) = target {
+                Style::default().fg(target).add_modifier(Modifier::DIM)
+            } else if let Some(onion) = palette::color(overlay.onion_at(x, y)) {
+                Style::default().fg(onion).add_modifier(Modifier::DIM)
+            } else if overlay.is_guide(x, y) {
+                Style::default().fg(GUIDE_COLOR).add_modifier(Modifier::DIM)
+            } else {
+                Style::default()
+            };
+            if highlighted && !overlay.is_cursor(x, y) && !overlay.is_peer(x, y) {
+                style = style.bg(SELECTION_COLOR);
+            }
+            spans.push(Span::styled(ch.to_string(), style));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+fn half_block_lines(cells: &Cells, overlay: &Overlay) -> Vec<Line<'static>> {
+    let color_at = |x: usize, y: usize| {
+        if overlay.is_cursor(x, y) {
+            return CURSOR_COLOR;
+        }
+        if overlay.is_peer(x, y) {
+            return PEER_COLOR;
+        }
+        let Some(row) = cells.get(y) else {
+            return Color::Reset;
+        };
+        palette::color(row[x]).unwrap_or(if overlay.is_highlighted(x, y) {
+            SELECTED_EMPTY_COLOR
+        } else if overlay.target_at(x, y) != palette::EMPTY {
+            TARGET_COLOR
+        } else if overlay.onion_at(x, y) != palette::EMPTY {
+            ONION_COLOR
+        } else if overlay.is_guide(x, y) {
+            GUIDE_COLOR
+        } else {
+            EMPTY_COLOR
+        })
+    };
+    let width = cells.first().map_or(0, Vec::len);
+    (0..cells.len())
+        .step_by(2)
+        .map(|y| {
+            let spans: Vec<Span> = (0..width)
+                .map(|x| {
+                    let style = Style::default().fg(color_at(x, y)).bg(color_at(x, y + 1));
+                    Span::styled("▀", style)
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn braille_lines(cells: &Cells, overlay: &Overlay) -> Vec<Line<'static>> {
+    // bit of each dot in a braille character, indexed by [dy][dx]
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    let width = cells.first().map_or(0, Vec::len);
+    let mut lines = Vec::new();
+    for cy in (0..cells.len()).step_by(4) {
+        let mut spans = Vec::new();
+        for cx in (0..width).step_by(2) {
+            let mut bits = 0;
+            let mut target_bits = 0;
+            let mut onion_bits = 0;
+            let mut guide_bits = 0;
+            let mut counts = [0usize; palette::PALETTE.len() + 1];
+            let mut has_cursor = false;
+            let mut has_peer = false;
+            let mut highlighted = false;
+            for (dy, row_dots) in DOTS.iter().enumerate() {
+                for (dx, &dot) in row_dots.iter().enumerate() {
+                    let (x, y) = (cx + dx, cy + dy);
+                    let Some(&cell) = cells.get(y).and_then(|row| row.get(x)) else {
+                        continue;
+                    };
+                    // the cursor dot is inverted so it stands out and still shows its state
+                    if (cell != palette::EMPTY) != overlay.is_cursor(x, y) {
+                        bits |= dot;
+                    }
+                    if overlay.target_at(x, y) != palette::EMPTY {
+                        target_bits |= dot;
+                    }
+                    if overlay.onion_at(x, y) != palette::EMPTY {
+                        onion_bits |= dot;
+                    }
+                    if overlay.is_guide(x, y) {
+                        guide_bits |= dot;
+                    }
+                    has_cursor |= overlay.is_cursor(x, y);
+                    has_peer |= overlay.is_peer(x, y);
+                    highlighted |= overlay.is_highlighted(x, y);
+                    counts[cell as usize] += 1;
+                }
+            }
+            // one color per character: the most used one among its filled dots
+            let color = (1..counts.len())
+                .max_by_key(|&i| (counts[i], std::cmp::Reverse(i)))
+                .filter(|&i| counts[i] > 0)
+                .and_then(|i| palette::color(i as u8));
+            let (bits, mut style) = if has_cursor {
+                (bits, Style::default().fg(Color::Yellow).bg(Color::Blue))
+            } else if has_peer {
+                (bits, Style::default().fg(Color::White).bg(PEER_COLOR))
+            } else if let Some(color) = color {
+                (bits, Style::default().fg(color))
+            } else if target_bits != 0 {
+                // only characters without dots of their own have room for the target or the
+                // onion skin
+                (target_bits, Style::default().fg(TARGET_COLOR))
+            } else if onion_bits != 0 {
+                (onion_bits, Style::default().fg(ONION_COLOR))
+            } else {
+                (guide_bits, Style::default().fg(GUIDE_COLOR))
+            };
+            if highlighted && !has_cursor && !has_peer {
+                style = style.bg(SELECTION_COLOR);
+            }
+            let ch = char::from_u32(0x2800 + bits).unwrap_or(' ');
+            spans.push(Span::styled(ch.to_string(), style));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// The puzzle board with its clues, every cell two characters wide so cells are about square
+/// and column clues have room for two digits. Clues of lines that match are dimmed.
+pub fn create_puzzle_text(grid: &Grid, puzzle: &Puzzle) -> Text<'static> {
+    let cells = if puzzle.solved {
+        &puzzle.solution
+    } else {
+        grid.cells()
+    };
+    let row_clues: Vec<String> = puzzle
+        .row_clues
+        .iter()
+        .map(|clues| {
+            let numbers: Vec<String> = clues.iter().map(usize::to_string).collect();
+            format!("{} ", numbers.join(" "))
+        })
+        .collect();
+    let clues_width = row_clues.iter().map(|c| c.len()).max().unwrap_or(0);
+    let clues_height = puzzle.column_clues.iter().map(Vec::len).max().unwrap_or(0);
+    let clue_style = |done: bool| {
+        if done {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default().fg(Color::Yellow)
+        }
+    };
+
+    let mut lines = Vec::new();
+    let columns_done: Vec<bool> = (0..grid.width)
+        .map(|x| {
+            let column: Vec<u8> = cells.iter().map(|row| row[x]).collect();
+            nonogram::clues(&column) == puzzle.column_clues[x]
+        })
+        .collect();
+    for i in 0..clues_height {
+        let mut spans = vec![Span::raw(" ".repeat(clues_width))];
+        for (clues, &done) in puzzle.column_clues.iter().zip(&columns_done) {
+            // clues sit at the bottom, right above the board
+            let number = (i + clues.len())
+                .checked_sub(clues_height)
+                .map_or(String::new(), |c| clues[c].to_string());
+            spans.push(Span::styled(format!("{:>2}", number), clue_style(done)));
+        }
+        lines.push(Line::from(spans));
+    }
+    for (y, row) in cells.iter().enumerate() {
+        let done = nonogram::clues(row) == puzzle.row_clues[y];
+        let mut spans = vec![Span::styled(
+            format!("{:>width$}", row_clues[y], width = clues_width),
+            clue_style(done),
+        )];
+        for (x, &cell) in row.iter().enumerate() {
+            let (text, mut style) = match palette::color(cell) {
+                // the colors stay hidden until the picture is revealed
+                Some(color) if puzzle.solved => ("██", Style::default().fg(color)),
+                Some(_) => ("██", Style::default().fg(Color::White)),
+                None if puzzle.marks[y][x] => ("><", Style::default().fg(Color::DarkGray)),
+                // every fifth line is darker to help counting
+                None if x / 5 % 2 == y / 5 % 2 => ("░░", Style::default()),
+                None => ("░░", Style::default().fg(Color::DarkGray)),
+            };
+            if !puzzle.solved && (x, y) == (grid.cursor_x, grid.cursor_y) {
+                style = style.bg(Color::Blue);
+                if cell == palette::EMPTY && !puzzle.marks[y][x] {
+                    style = style.fg(Color::Yellow);
+                }
+            }
+            spans.push(Span::styled(text, style));
+        }
+        lines.push(Line::from(spans));
+    }
+    Text::from(lines)
+}
+
+pub fn create_palette_line(grid: &Grid) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    for (i, &color) in palette::PALETTE.iter().enumerate() {
+        let index = i as u8 + 1;
+        let (open, close) = if index == grid.color {
+            ('[', ']')
+        } else {
+            (' ', ' ')
+        };
+        spans.push(Span::styled(
+            format!("{}{}", open, index),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::styled("██", Style::default().fg(color)));
+        spans.push(Span::raw(close.to_string()));
+    }
+    Line::from(spans)
+}
+
+/// A small overview of the whole canvas with the visible part highlighted, each character
+/// covering a block of cells; `width` and `height` must not exceed the canvas size.
+pub fn create_minimap_text(
+    grid: &Grid,
+    view: Viewport,
+    width: usize,
+    height: usize,
+) -> Text<'static> {
+    let block = |i: usize, n: usize, total: usize| i * total / n..(i + 1) * total / n;
+    let mut lines = Vec::new();
+    for my in 0..height {
+        let ys = block(my, height, grid.height);
+        let mut spans = Vec::new();
+        for mx in 0..width {
+            let xs = block(mx, width, grid.width);
+            let has_cursor = xs.contains(&grid.cursor_x) && ys.contains(&grid.cursor_y);
+            let filled = grid.cells()[ys.clone()]
+                .iter()
+                .any(|row| row[xs.clone()].iter().any(|&c| c != palette::EMPTY));
+            let visible = xs.end > view.x
+                && xs.start < view.x + view.width
+                && ys.end > view.y
+                && ys.start < view.y + view.height;
+            let ch = if has_cursor {
+                '+'
+            } else if filled {
+                '▪'
+            } else {
+                ' '
+            };
+            let mut style = Style::default();
+            if visible {
+                style = style.bg(Color::DarkGray);
+            }
+            if has_cursor {
+                style = style.fg(Color::Yellow);
+            }
+            spans.push(Span::styled(ch.to_string(), style));
+        }
+        lines.push(Line::from(spans));
+    }
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolls_just_enough_to_show_the_cursor() {
+        assert_eq!(scroll(0, 10, 4, 30), 0);
+        assert_eq!(scroll(0, 10, 10, 30), 1);
+        assert_eq!(scroll(5, 10, 2, 30), 2);
+        assert_eq!(scroll(5, 10, 14, 30), 5);
+        assert_eq!(scroll(25, 10, 29, 30), 20);
+        assert_eq!(scroll(0, 30, 29, 30), 0);
+    }
+
+    #[test]
+    fn follows_the_cursor_in_every_mode() {
+        let mut grid = Grid::new(40, 40);
+        (grid.cursor_x, grid.cursor_y) = (39, 39);
+        let mut view = Viewport::default();
+        view.follow(&grid, RenderMode::Braille, Rect::new(0, 0, 10, 5));
+        assert_eq!((view.x, view.y, view.width, view.height), (20, 20, 20, 20));
+        view.follow(&grid, RenderMode::Cells, Rect::new(0, 0, 50, 50));
+        assert!(view.shows_all(&grid));
+        assert_eq!((view.x, view.y), (0, 0));
+    }
+
+    #[test]
+    fn crops_to_the_viewport() {
+        let cells: Cells = (0..4)
+            .map(|y| (0..4).map(|x| x + 4 * y).collect())
+            .collect();
+        let view = Viewport {
+            x: 1,
+            y: 2,
+            width: 2,
+            height: 2,
+        };
+        assert_eq!(crop(&cells, view), [vec![9, 10], vec![13, 14]]);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{grid::Cells, palette::EMPTY};
+
+/// An inclusive rectangle of cells.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Area {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Area {
+    /// The rectangle with corners `a` and `b`, in any order.
+    pub fn spanning(a: (usize, usize), b: (usize, usize)) -> Self {
+        Area {
+            x0: a.0.min(b.0),
+            y0: a.1.min(b.1),
+            x1: a.0.max(b.0),
+            y1: a.1.max(b.1),
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x0..=self.x1).contains(&x) && (self.y0..=self.y1).contains(&y)
+    }
+
+    pub fn copy(&self, cells: &Cells) -> Cells {
+        cells[self.y0..=self.y1]
+            .iter()
+            .map(|row| row[self.x0..=self.x1].to_vec())
+            .collect()
+    }
+
+    pub fn clear(&self, cells: &mut Cells) {
+        for row in &mut cells[self.y0..=self.y1] {
+            row[self.x0..=self.x1].fill(EMPTY);
+        }
+    }
+}
+
+/// Cells floating over the canvas with their top-left corner at the cursor until committed.
+/// Empty cells are transparent.
+pub struct Stamp {
+    pub cells: Cells,
+    /// Where the cells were lifted from, cleared when the stamp is committed.
+    pub lifted_from: Option<Area>,
+}
+
+impl Stamp {
+    /// Draws the stamp with its top-left corner at `(x, y)`, clipped to the canvas.
+    pub fn draw(&self, cells: &mut Cells, x: usize, y: usize) {
+        if let Some(area) = self.lifted_from {
+            area.clear(cells);
+        }
+        for (row, stamp_row) in cells.iter_mut().skip(y).zip(&self.cells) {
+            for (cell, &value) in row.iter_mut().skip(x).zip(stamp_row) {
+                if value != EMPTY {
+                    *cell = value;
+                }
+            }
+        }
+    }
+}
+
+/// Mirrors left to right.
+pub fn flip_horizontal(cells: &Cells) -> Cells {
+    cells
+        .iter()
+        .map(|row| row.iter().rev().copied().collect())
+        .collect()
+}
+
+/// Mirrors top to bottom.
+pub fn flip_vertical(cells: &Cells) -> Cells {
+    cells.iter().rev().cloned().collect()
+}
+
+/// Rotates 90° clockwise.
+pub fn rotate(cells: &Cells) -> Cells {
+    let height = cells.len();
+    let width = cells.first().map_or(0, Vec::len);
+    (0..width)
+        .map(|x| (0..height).map(|y| cells[height - 1 - y][x]).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_non_square_cells_clockwise() {
+        let cells = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(rotate(&cells), [vec![4, 1], vec![5, 2], vec![6, 3]]);
+        assert_eq!(rotate(&rotate(&rotate(&rotate(&cells)))), cells);
+    }
+
+    #[test]
+    fn flips() {
+        let cells = vec![vec![1, 2], vec![3, EMPTY]];
+        assert_eq!(flip_horizontal(&cells), [vec![2, 1], vec![EMPTY, 3]]);
+        assert_eq!(flip_vertical(&cells), [vec![3, EMPTY], vec![1, 2]]);
+    }
+
+    #[test]
+    fn copies_and_clears_areas() {
+        let mut cells = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let area = Area::spanning((2, 1), (1, 0));
+        assert!(area.contains(1, 1) && !area.contains(0, 0));
+        assert_eq!(area.copy(&cells), [vec![2, 3], vec![5, 6]]);
+        area.clear(&mut cells);
+        assert_eq!(cells, [vec![1, EMPTY, EMPTY], vec![4, EMPTY, EMPTY]]);
+    }
+
+    #[test]
+    fn stamps_leave_cells_under_empty_ones() {
+        let mut cells = vec![vec![7; 3]; 2];
+        let stamp = Stamp {
+            cells: vec![vec![1, EMPTY], vec![EMPTY, 2]],
+            lifted_from: None,
+        };
+        stamp.draw(&mut cells, 1, 0);
+        assert_eq!(cells, [vec![7, 1, 7], vec![7, 7, 2]]);
+    }
+
+    #[test]
+    fn lifted_stamps_clear_where_they_came_from() {
+        let mut cells = vec![vec![1, 2, EMPTY], vec![EMPTY; 3]];
+        let area = Area::spanning((0, 0), (1, 0));
+        let stamp = Stamp {
+            cells: area.copy(&cells),
+            lifted_from: Some(area),
+        };
+        stamp.draw(&mut cells, 1, 1);
+        assert_eq!(cells, [vec![EMPTY; 3], vec![EMPTY, 1, 2]]);
+    }
+
+    #[test]
+    fn stamps_are_clipped_at_the_edges() {
+        let mut cells = vec![vec![EMPTY; 2]; 2];
+        let stamp = Stamp {
+            cells: vec![vec![1, 2], vec![3, 4]],
+            lifted_from: None,
+        };
+        stamp.draw(&mut cells, 1, 1);
+        assert_eq!(cells, [vec![EMPTY, EMPTY], vec![EMPTY, 1]]);
+    }
+}
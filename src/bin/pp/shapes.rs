@@ -0,0 +1,108 @@
+/// Cells of the Bresenham line from `(x0, y0)` to `(x1, y1)`, both ends included.
+pub fn line(x0: usize, y0: usize, x1: usize, y1: usize) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (x0 as isize, y0 as isize);
+    let (x1, y1) = (x1 as isize, y1 as isize);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let sx = if x < x1 { 1 } else { -1 };
+    let sy = if y < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut points = Vec::new();
+    loop {
+        points.push((x as usize, y as usize));
+        if x == x1 && y == y1 {
+            return points;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    Line,
+    Rect { filled: bool },
+    Ellipse { filled: bool },
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Line => "Line",
+            Tool::Rect { filled: false } => "Rectangle",
+            Tool::Rect { filled: true } => "Filled Rectangle",
+            Tool::Ellipse { filled: false } => "Ellipse",
+            Tool::Ellipse { filled: true } => "Filled Ellipse",
+        }
+    }
+
+    /// Cells of the shape spanning from the anchor `a` to the cursor `b`.
+    pub fn points(self, a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
+        let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+        let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+        match self {
+            Tool::Line => line(a.0, a.1, b.0, b.1),
+            Tool::Rect { filled } => (y0..=y1)
+                .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+                .filter(|&(x, y)| filled || x == x0 || x == x1 || y == y0 || y == y1)
+                .collect(),
+            Tool::Ellipse { filled } => {
+                // a cell is inside when its center is inside the ellipse inscribed in the box
+                let cx = (x0 + x1) as f64 / 2.0;
+                let cy = (y0 + y1) as f64 / 2.0;
+                let rx = (x1 - x0) as f64 / 2.0 + 0.5;
+                let ry = (y1 - y0) as f64 / 2.0 + 0.5;
+                let inside = |x: isize, y: isize| {
+                    let dx = (x as f64 - cx) / rx;
+                    let dy = (y as f64 - cy) / ry;
+                    dx * dx + dy * dy <= 1.0
+                };
+                (y0..=y1)
+                    .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+                    .filter(|&(x, y)| {
+                        let (x, y) = (x as isize, y as isize);
+                        inside(x, y)
+                            && (filled
+                                || !inside(x - 1, y)
+                                || !inside(x + 1, y)
+                                || !inside(x, y - 1)
+                                || !inside(x, y + 1))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A shape being previewed, from its anchor to the cursor.
+pub struct Shape {
+    pub tool: Tool,
+    pub anchor: (usize, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_include_both_ends() {
+        assert_eq!(line(1, 1, 1, 1), [(1, 1)]);
+        assert_eq!(line(0, 2, 3, 2), [(0, 2), (1, 2), (2, 2), (3, 2)]);
+        assert_eq!(line(3, 3, 0, 0), [(3, 3), (2, 2), (1, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn steep_lines_have_a_cell_per_row() {
+        let points = line(0, 0, 2, 5);
+        assert_eq!(points.len(), 6);
+        assert!(points.iter().enumerate().all(|(i, &(_, y))| y == i));
+        assert_eq!(points.last(), Some(&(2, 5)));
+    }
+}
@@ -0,0 +1,141 @@
+//! Ready-made pictures to stamp onto drawings: the built-in ones and the user's own, saved as
+//! drawings in their data directory.
+
+use crate::{
+    file, font,
+    grid::{Cells, Grid},
+    palette,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Built-in stamps as rows of palette indexes, `.` for empty cells.
+#[rustfmt::skip]
+const BUILT_IN: &[(&str, &[&str])] = &[
+    ("heart", &[".11.11.", "1111111", "1111111", ".11111.", "..111..", "...1..."]),
+    ("small heart", &["1.1", "111", ".1."]),
+    ("star", &["...4...", "...4...", "4444444", ".44444.", "..444..", ".44.44.", "44...44"]),
+    ("sparkle", &["..4..", "..4..", "44444", "..4..", "..4.."]),
+    ("sun", &["4..4..4", ".4.4.4.", "..777..", "4477744", "..777..", ".4.4.4.", "4..4..4"]),
+    ("flower", &[".5.5.", "55455", ".5.5.", "..3..", "3.3.3", ".333.", "..3.."]),
+    ("tree", &["..333..", ".33333.", "3333333", ".33333.", "...7...", "...7..."]),
+    ("smiley", &[".44444.", "4494944", "4444444", "4944494", "4499944", ".44444."]),
+    ("cat", &["7...7", "77777", "73737", "77977", ".777."]),
+    ("dog", &["9977799", "9777779", "7977797", "7779777", ".77177.", "..777.."]),
+    ("fish", &["..666..6", ".69666.6", "66666666", ".66666.6", "..666..6"]),
+    ("bird", &["....22.", "...2294", "22222..", ".22222.", "..4.4.."]),
+    ("bunny", &[".8.8.", ".8.8.", ".888.", "81818", "88888", ".888."]),
+    ("car", &["..11111..", ".1221221.", "111111111", ".99...99."]),
+    ("boat", &["...9...", "..89...", ".889...", "8889...", "...9...", "7777777", ".77777."]),
+    ("rocket", &["..1..", ".888.", ".828.", ".888.", ".888.", "18881", "1.4.1", "..7.."]),
+    ("plane", &["...8...", "..888..", "8888888", "...8...", "..888.."]),
+];
+
+pub struct Entry {
+    pub name: String,
+    pub cells: Cells,
+    /// File of one of the user's stamps, built-in ones have none.
+    pub path: Option<PathBuf>,
+}
+
+/// All stamps as listed in the side panel.
+pub struct Library {
+    pub entries: Vec<Entry>,
+    /// Index of the stamp being looked at.
+    pub selected: usize,
+}
+
+impl Library {
+    /// The built-in stamps, letters in `color`, followed by the user's.
+    pub fn load(color: u8) -> Self {
+        let pictures = BUILT_IN.iter().map(|(name, rows)| Entry {
+            name: name.to_string(),
+            cells: palette::parse_rows(rows),
+            path: None,
+        });
+        let letters = ('A'..='Z').map(|c| Entry {
+            name: format!("letter {}", c),
+            cells: font::render(&c.to_string(), color, 1),
+            path: None,
+        });
+        let mut entries: Vec<_> = pictures.chain(letters).collect();
+        entries.extend(dir().map_or_else(Vec::new, |dir| user_stamps(&dir)));
+        Library {
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Entry {
+        &self.entries[self.selected]
+    }
+
+    pub fn select(&mut self, delta: isize) {
+        let len = self.entries.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Deletes the selected stamp if it is one of the user's.
+    pub fn remove_current(&mut self) -> io::Result<bool> {
+        let Some(path) = &self.current().path else {
+            return Ok(false);
+        };
+        fs::remove_file(path)?;
+        self.entries.remove(self.selected);
+        self.selected = self.selected.min(self.entries.len() - 1);
+        Ok(true)
+    }
+}
+
+/// Where the user's stamps are kept, `None` when the platform has no place for them.
+fn dir() -> Option<PathBuf> {
+    Some(
+        dirs::data_local_dir()?
+            .join("kli")
+            .join("pp")
+            .join("stamps"),
+    )
+}
+
+/// The stamps in `dir` sorted by name, skipping files that can't be read.
+fn user_stamps(dir: &Path) -> Vec<Entry> {
+    let Ok(files) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = files
+        .filter_map(|file| Some(file.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "kli"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let grid = file::load(&path).ok()?;
+            Some(Entry {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                cells: grid.cells().clone(),
+                path: Some(path),
+            })
+        })
+        .collect()
+}
+
+/// Adds `cells` to the user's stamps under the first free `my-stamp-N` name, which is returned.
+pub fn save(cells: &Cells) -> io::Result<String> {
+    let dir = dir().ok_or_else(|| io::Error::other("no data directory to keep stamps in"))?;
+    fs::create_dir_all(&dir)?;
+    let (name, path) = (1..)
+        .map(|n| {
+            let name = format!("my-stamp-{}", n);
+            let path = dir.join(&name).with_extension("kli");
+            (name, path)
+        })
+        .find(|(_, path)| !path.exists())
+        .expect("some name is free");
+    let mut grid = Grid::new(cells[0].len(), cells.len());
+    grid.set_cells(cells.clone());
+    file::save(&grid, &path)?;
+    Ok(name)
+}
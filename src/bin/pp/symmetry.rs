@@ -0,0 +1,122 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    Off,
+    /// Left and right halves mirror each other.
+    Horizontal,
+    /// Top and bottom halves mirror each other.
+    Vertical,
+    /// All four quarters mirror each other.
+    FourWay,
+}
+
+impl Symmetry {
+    pub fn next(self) -> Self {
+        match self {
+            Symmetry::Off => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::FourWay,
+            Symmetry::FourWay => Symmetry::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::Off => "off",
+            Symmetry::Horizontal => "horizontal",
+            Symmetry::Vertical => "vertical",
+            Symmetry::FourWay => "four-way",
+        }
+    }
+
+    fn mirrors_x(self) -> bool {
+        matches!(self, Symmetry::Horizontal | Symmetry::FourWay)
+    }
+
+    fn mirrors_y(self) -> bool {
+        matches!(self, Symmetry::Vertical | Symmetry::FourWay)
+    }
+
+    /// The cell itself and its mirror images on a `width`x`height` canvas, without duplicates.
+    pub fn images(self, x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
+        let xs = if self.mirrors_x() {
+            vec![x, width - 1 - x]
+        } else {
+            vec![x]
+        };
+        let ys = if self.mirrors_y() {
+            vec![y, height - 1 - y]
+        } else {
+            vec![y]
+        };
+        let mut images = Vec::new();
+        for &y in &ys {
+            for &x in &xs {
+                if !images.contains(&(x, y)) {
+                    images.push((x, y));
+                }
+            }
+        }
+        images
+    }
+
+    /// Columns next to the vertical axis, empty when there is none.
+    pub fn guide_columns(self, width: usize) -> Range<usize> {
+        if self.mirrors_x() {
+            axis(width)
+        } else {
+            0..0
+        }
+    }
+
+    /// Rows next to the horizontal axis, empty when there is none.
+    pub fn guide_rows(self, height: usize) -> Range<usize> {
+        if self.mirrors_y() {
+            axis(height)
+        } else {
+            0..0
+        }
+    }
+}
+
+/// The middle line, or the two lines the axis runs between on an even length.
+fn axis(len: usize) -> Range<usize> {
+    (len - 1) / 2..len / 2 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_across_the_middle() {
+        assert_eq!(Symmetry::Off.images(1, 2, 5, 4), [(1, 2)]);
+        assert_eq!(Symmetry::Horizontal.images(1, 2, 5, 4), [(1, 2), (3, 2)]);
+        assert_eq!(Symmetry::Vertical.images(1, 2, 5, 4), [(1, 2), (1, 1)]);
+        assert_eq!(Symmetry::Horizontal.images(0, 0, 4, 4), [(0, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn four_way_makes_four_images() {
+        let images = Symmetry::FourWay.images(0, 1, 4, 5);
+        assert_eq!(images, [(0, 1), (3, 1), (0, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn cells_on_the_axis_are_not_repeated() {
+        assert_eq!(Symmetry::Horizontal.images(2, 0, 5, 1), [(2, 0)]);
+        assert_eq!(Symmetry::FourWay.images(2, 1, 5, 4), [(2, 1), (2, 2)]);
+        assert_eq!(Symmetry::FourWay.images(2, 2, 5, 5), [(2, 2)]);
+    }
+
+    #[test]
+    fn guides_run_along_the_axes() {
+        assert_eq!(axis(5), 2..3);
+        assert_eq!(axis(4), 1..3);
+        assert_eq!(axis(1), 0..1);
+        assert_eq!(Symmetry::Vertical.guide_columns(4), 0..0);
+        assert_eq!(Symmetry::Vertical.guide_rows(4), 1..3);
+        assert_eq!(Symmetry::FourWay.guide_columns(5), 2..3);
+    }
+}
@@ -0,0 +1,242 @@
+//! Tracing practice: a target picture is shown faintly under the canvas and the drawing is
+//! scored by how closely its filled cells cover the target's.
+
+use crate::{
+    file, font,
+    grid::Cells,
+    palette::{self, EMPTY},
+};
+use std::path::Path;
+
+/// Palette index of letters and digits used as targets.
+const LETTER_COLOR: u8 = 2;
+
+/// Built-in targets as rows of palette indexes, `.` for empty cells.
+#[rustfmt::skip]
+const PICTURES: &[(&str, &[&str])] = &[
+    ("circle", &[
+        "...222...",
+        ".2222222.",
+        ".2222222.",
+        "222222222",
+        "222222222",
+        "222222222",
+        ".2222222.",
+        ".2222222.",
+        "...222...",
+    ]),
+    ("square", &[
+        "33333333",
+        "3......3",
+        "3......3",
+        "3......3",
+        "3......3",
+        "3......3",
+        "3......3",
+        "33333333",
+    ]),
+    ("triangle", &[
+        "....7....",
+        "...777...",
+        "...777...",
+        "..77777..",
+        "..77777..",
+        ".7777777.",
+        ".7777777.",
+        "777777777",
+    ]),
+    ("heart", &[
+        ".11...11.",
+        "1111.1111",
+        "111111111",
+        "111111111",
+        ".1111111.",
+        "..11111..",
+        "...111...",
+        "....1....",
+    ]),
+    ("star", &[
+        ".....4.....",
+        "....444....",
+        "....444....",
+        "44444444444",
+        ".444444444.",
+        "..4444444..",
+        "...44444...",
+        "..444.444..",
+        ".44.....44.",
+    ]),
+    ("house", &[
+        "....11....",
+        "...1111...",
+        "..111111..",
+        ".11111111.",
+        "1111111111",
+        ".44444444.",
+        ".44422444.",
+        ".44422444.",
+        ".44422444.",
+    ]),
+    ("cat", &[
+        "7.......7",
+        "77.....77",
+        "777777777",
+        "773777377",
+        "777777777",
+        "777797777",
+        ".7777777.",
+        "..77777..",
+    ]),
+    ("fish", &[
+        "....6666.....",
+        "..66666666..6",
+        ".6966666666.6",
+        "666666666666.",
+        ".6666666666.6",
+        "..66666666..6",
+        "....6666.....",
+    ]),
+    ("duck", &[
+        "..444.....",
+        ".44944....",
+        ".4444477..",
+        "..444.....",
+        ".44444444.",
+        "4444444444",
+        ".44444444.",
+        "..444444..",
+    ]),
+];
+
+pub struct Trace {
+    /// What is being traced, as given on the command line.
+    pub name: String,
+    /// Score of the last time the drawing was handed in, shown until dismissed.
+    pub result: Option<u32>,
+}
+
+/// Names of the built-in targets.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PICTURES.iter().map(|(name, _)| *name)
+}
+
+/// The target called `name`: a built-in picture, a single letter or digit or a drawing file.
+/// The built-in names come first, so a file that happens to share one doesn't hide it.
+pub fn load(name: &str) -> Result<Cells, String> {
+    if let Some((_, rows)) = PICTURES.iter().find(|(picture, _)| *picture == name) {
+        return Ok(palette::parse_rows(rows));
+    }
+    let path = Path::new(name);
+    match name.chars().collect::<Vec<_>>()[..] {
+        [c] if c.is_ascii_alphanumeric() => Ok(font::render(name, LETTER_COLOR, 1)),
+        _ if path.exists() => file::load(path)
+            .map(|grid| grid.cells().clone())
+            .map_err(|err| err.to_string()),
+        _ => Err(format!(
+            "no such drawing, and not a letter, a digit or one of {}",
+            names().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Centers `target` on a `width`x`height` canvas, enlarged as much as whole cells allow and
+/// cropped when it doesn't fit.
+pub fn fit(target: &Cells, width: usize, height: usize) -> Cells {
+    let target_width = target.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let target_height = target.len().max(1);
+    let scale = (width / target_width).min(height / target_height).max(1);
+    let (scaled_width, scaled_height) = (target_width * scale, target_height * scale);
+    let mut cells = vec![vec![EMPTY; width]; height];
+    for (y, row) in cells.iter_mut().enumerate() {
+        let Some(ty) = (y + scaled_height / 2).checked_sub(height / 2) else {
+            continue;
+        };
+        for (x, cell) in row.iter_mut().enumerate() {
+            let Some(tx) = (x + scaled_width / 2).checked_sub(width / 2) else {
+                continue;
+            };
+            if tx < scaled_width && ty < scaled_height {
+                let value = target[ty / scale].get(tx / scale);
+                *cell = value.copied().unwrap_or(EMPTY);
+            }
+        }
+    }
+    cells
+}
+
+/// Percentage of the cells filled in the target or the drawing that are filled in both, so
+/// both missed and stray cells lower it; colors don't matter.
+pub fn accuracy(cells: &Cells, target: &Cells) -> u32 {
+    let (mut both, mut either) = (0, 0);
+    for (row, target_row) in cells.iter().zip(target) {
+        for (&cell, &target_cell) in row.iter().zip(target_row) {
+            let (filled, wanted) = (cell != EMPTY, target_cell != EMPTY);
+            both += (filled && wanted) as u32;
+            either += (filled || wanted) as u32;
+        }
+    }
+    match either {
+        0 => 100,
+        _ => both * 100 / either,
+    }
+}
+
+/// Stars out of 3 earned with an accuracy.
+pub fn stars(accuracy: u32) -> usize {
+    match accuracy {
+        90.. => 3,
+        70.. => 2,
+        40.. => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use std::fs;
+
+    #[test]
+    fn accuracy_counts_missed_and_stray_cells() {
+        let target = vec![vec![1, 1, EMPTY, EMPTY]];
+        assert_eq!(accuracy(&target, &target), 100);
+        assert_eq!(accuracy(&vec![vec![EMPTY, EMPTY, 2, 2]], &target), 0);
+        // colors don't matter, one of the three cells filled anywhere is shared
+        assert_eq!(accuracy(&vec![vec![5, EMPTY, 5, EMPTY]], &target), 33);
+        let blank = vec![vec![EMPTY; 4]];
+        assert_eq!(accuracy(&blank, &blank), 100);
+    }
+
+    #[test]
+    fn stars_follow_accuracy() {
+        assert_eq!([100, 90, 89, 70, 40, 39].map(stars), [3, 3, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn fit_centers_and_enlarges() {
+        let target = vec![vec![1]];
+        let fitted = fit(&target, 5, 2);
+        assert_eq!(fitted.len(), 2);
+        assert_eq!(fitted[0], [EMPTY, 1, 1, EMPTY, EMPTY]);
+        assert_eq!(fitted[1], fitted[0]);
+    }
+
+    #[test]
+    fn loads_built_in_pictures_and_letters() {
+        assert!(names().all(|name| load(name).is_ok()));
+        assert!(load("A").is_ok());
+        assert!(load("no such thing").is_err());
+    }
+
+    #[test]
+    fn loads_drawing_files() {
+        let mut grid = Grid::new(2, 1);
+        grid.frames[0][0][1] = 4;
+        let path = std::env::temp_dir().join(format!("pp-trace-{}.kli", std::process::id()));
+        file::save(&grid, &path).unwrap();
+        let target = load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(target.unwrap(), vec![vec![EMPTY, 4]]);
+    }
+}
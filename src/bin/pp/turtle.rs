@@ -0,0 +1,444 @@
+//! A tiny LOGO driving the cursor: programs like `repeat 4 [forward 5 right 90]` walk a turtle
+//! over the canvas, painting with the active color behind it while its pen is down.
+//!
+//! Commands are `forward`/`fd`, `back`/`bk`, `right`/`rt` and `left`/`lt` with a distance in
+//! cells or an angle in degrees, `penup`/`pu`, `pendown`/`pd`, `color` with a palette number
+//! and `repeat` with a count and a block in brackets. `;` starts a comment.
+
+use crate::grid::Grid;
+use std::{collections::VecDeque, fmt};
+
+/// Cells walked per second while a program runs.
+pub const SPEED: u64 = 30;
+/// Most steps a program may take, every cell walked counting as one.
+const MAX_STEPS: usize = 100_000;
+
+/// A mistake in a program, at a 1-based line and column.
+pub struct SyntaxError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+enum Command {
+    Forward(f64),
+    Turn(f64),
+    Pen(bool),
+    Color(u8),
+    Repeat(usize, Vec<Command>),
+}
+
+pub struct Program(Vec<Command>);
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Open,
+    Close,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Number(n) => write!(f, "`{}`", n),
+            Token::Open => write!(f, "`[`"),
+            Token::Close => write!(f, "`]`"),
+            Token::End => write!(f, "the end of the program"),
+        }
+    }
+}
+
+/// Tokens with their line and column; the last one is always `End`.
+fn tokenize(source: &str) -> Vec<(Token, usize, usize)> {
+    let mut tokens = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let start = (line, column);
+        let mut advance = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let c = chars.next();
+            if c == Some('\n') {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            c
+        };
+        let token = match c {
+            ';' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    advance(&mut chars);
+                }
+                continue;
+            }
+            c if c.is_whitespace() => {
+                advance(&mut chars);
+                continue;
+            }
+            '[' | ']' => {
+                advance(&mut chars);
+                if c == '[' {
+                    Token::Open
+                } else {
+                    Token::Close
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '[' | ']' | ';') {
+                        break;
+                    }
+                    word.push(c);
+                    advance(&mut chars);
+                }
+                match word.parse() {
+                    Ok(n) if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                        Token::Number(n)
+                    }
+                    _ => Token::Word(word.to_lowercase()),
+                }
+            }
+        };
+        tokens.push((token, start.0, start.1));
+    }
+    tokens.push((Token::End, line, column));
+    tokens
+}
+
+pub fn parse(source: &str) -> Result<Program, SyntaxError> {
+    let mut parser = Parser {
+        tokens: tokenize(source),
+        pos: 0,
+    };
+    let (commands, _) = parser.block(None)?;
+    Ok(Program(commands))
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> (Token, usize, usize) {
+        let token = self.tokens[self.pos].clone();
+        // stay on `End` once there
+        self.pos = (self.pos + 1).min(self.tokens.len() - 1);
+        token
+    }
+
+    fn error(line: usize, column: usize, message: String) -> SyntaxError {
+        SyntaxError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    /// Commands up to the end of the program, or up to the `]` closing the `[` at `open`, with
+    /// the number of steps they take.
+    fn block(
+        &mut self,
+        open: Option<(usize, usize)>,
+    ) -> Result<(Vec<Command>, usize), SyntaxError> {
+        let mut commands = Vec::new();
+        let mut steps: usize = 0;
+        loop {
+            let (token, line, column) = self.next();
+            let word = match token {
+                Token::Word(word) => word,
+                Token::End => {
+                    return match open {
+                        None => Ok((commands, steps)),
+                        Some((line, column)) => Err(Self::error(
+                            line,
+                            column,
+                            "this `[` is never closed by a `]`".to_string(),
+                        )),
+                    };
+                }
+                Token::Close if open.is_some() => return Ok((commands, steps)),
+                token => {
+                    return Err(Self::error(
+                        line,
+                        column,
+                        format!("expected a command, found {}", token),
+                    ))
+                }
+            };
+            let (command, cost) = match word.as_str() {
+                "forward" | "fd" | "back" | "bk" => {
+                    let distance = self.number(&word)?;
+                    let sign = if matches!(word.as_str(), "back" | "bk") {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    let cost = (distance.abs().ceil() as usize).max(1);
+                    (Command::Forward(sign * distance), cost)
+                }
+                "right" | "rt" => (Command::Turn(self.number(&word)?), 1),
+                "left" | "lt" => (Command::Turn(-self.number(&word)?), 1),
+                "penup" | "pu" => (Command::Pen(false), 1),
+                "pendown" | "pd" => (Command::Pen(true), 1),
+                "color" => {
+                    let (token, line, column) = self.tokens[self.pos].clone();
+                    match self.number(&word)? {
+                        n if (1.0..=9.0).contains(&n) && n.fract() == 0.0 => {
+                            (Command::Color(n as u8), 1)
+                        }
+                        _ => {
+                            let message = format!("colors go from 1 to 9, not {}", token);
+                            return Err(Self::error(line, column, message));
+                        }
+                    }
+                }
+                "repeat" => {
+                    let (token, line, column) = self.tokens[self.pos].clone();
+                    let count = match self.number(&word)? {
+                        n if n >= 0.0 && n.fract() == 0.0 => n as usize,
+                        _ => {
+                            let message = format!("can't repeat {} times", token);
+                            return Err(Self::error(line, column, message));
+                        }
+                    };
+                    let (token, line, column) = self.next();
+                    if token != Token::Open {
+                        let message =
+                            format!("expected `[` after `repeat {}`, found {}", count, token);
+                        return Err(Self::error(line, column, message));
+                    }
+                    let (body, cost) = self.block(Some((line, column)))?;
+                    (
+                        Command::Repeat(count, body),
+                        count.saturating_mul(cost.max(1)),
+                    )
+                }
+                _ => {
+                    return Err(Self::error(
+                        line,
+                        column,
+                        format!("unknown command `{}`", word),
+                    ))
+                }
+            };
+            steps = steps.saturating_add(cost);
+            if steps > MAX_STEPS {
+                let message = format!("this would take more than {} steps", MAX_STEPS);
+                return Err(Self::error(line, column, message));
+            }
+            commands.push(command);
+        }
+    }
+
+    /// The number following the command `word`.
+    fn number(&mut self, word: &str) -> Result<f64, SyntaxError> {
+        match self.next() {
+            (Token::Number(n), _, _) => Ok(n),
+            (token, line, column) => Err(Self::error(
+                line,
+                column,
+                format!("`{}` needs a number, found {}", word, token),
+            )),
+        }
+    }
+}
+
+/// Where the turtle is, in cells that may be off the canvas, and where it is heading in degrees
+/// clockwise from up.
+#[derive(Clone, Copy)]
+pub struct Turtle {
+    pub x: f64,
+    pub y: f64,
+    heading: f64,
+    pen: bool,
+}
+
+impl Turtle {
+    /// A turtle on a cell, heading up with its pen down.
+    pub fn new(x: usize, y: usize) -> Self {
+        Turtle {
+            x: x as f64,
+            y: y as f64,
+            heading: 0.0,
+            pen: true,
+        }
+    }
+
+    /// The cell the turtle is on, if it is on the canvas.
+    fn cell(&self, grid: &Grid) -> Option<(usize, usize)> {
+        let (x, y) = (self.x.round(), self.y.round());
+        (x >= 0.0 && y >= 0.0 && (x as usize) < grid.width && (y as usize) < grid.height)
+            .then_some((x as usize, y as usize))
+    }
+
+    /// Whether the turtle is on the cursor cell, so a new program can carry on from it.
+    pub fn is_at_cursor(&self, grid: &Grid) -> bool {
+        self.cell(grid) == Some((grid.cursor_x, grid.cursor_y))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Forward(f64),
+    Turn(f64),
+    Pen(bool),
+    Color(u8),
+}
+
+/// A program being played, one cell at a time.
+pub struct Run {
+    steps: Vec<Step>,
+    next: usize,
+    pub turtle: Turtle,
+    /// Points left on the line being walked.
+    path: VecDeque<(f64, f64)>,
+}
+
+impl Run {
+    pub fn new(program: &Program, turtle: Turtle) -> Self {
+        let mut steps = Vec::new();
+        flatten(&program.0, &mut steps);
+        Run {
+            steps,
+            next: 0,
+            turtle,
+            path: VecDeque::new(),
+        }
+    }
+
+    /// Plays the program until the turtle has walked one cell, false once it is over.
+    pub fn advance(&mut self, grid: &mut Grid) -> bool {
+        loop {
+            if let Some((x, y)) = self.path.pop_front() {
+                self.turtle.x = x;
+                self.turtle.y = y;
+                self.visit(grid);
+                return true;
+            }
+            let Some(&step) = self.steps.get(self.next) else {
+                return false;
+            };
+            self.next += 1;
+            match step {
+                Step::Forward(distance) => {
+                    let Turtle { x, y, heading, .. } = self.turtle;
+                    let radians = heading.to_radians();
+                    let (dx, dy) = (distance * radians.sin(), -distance * radians.cos());
+                    let n = dx.abs().max(dy.abs()).round().max(1.0) as usize;
+                    // the starting cell is painted too when the pen just went down there
+                    let first = if self.turtle.pen { 0 } else { 1 };
+                    self.path = (first..=n)
+                        .map(|i| {
+                            let t = i as f64 / n as f64;
+                            (x + dx * t, y + dy * t)
+                        })
+                        .collect();
+                }
+                Step::Turn(angle) => {
+                    self.turtle.heading = (self.turtle.heading + angle).rem_euclid(360.0)
+                }
+                Step::Pen(down) => self.turtle.pen = down,
+                Step::Color(color) => grid.color = color,
+            }
+        }
+    }
+
+    /// Plays the rest of the program at once.
+    pub fn finish(&mut self, grid: &mut Grid) {
+        while self.advance(grid) {}
+    }
+
+    /// Moves the cursor after the turtle, painting its cell when the pen is down; off the canvas
+    /// the cursor waits at the edge.
+    fn visit(&self, grid: &mut Grid) {
+        match self.turtle.cell(grid) {
+            Some(cell) if self.turtle.pen => grid.paint_line(cell, cell, grid.color),
+            Some((x, y)) => (grid.cursor_x, grid.cursor_y) = (x, y),
+            None => {
+                let max_x = grid.width as f64 - 1.0;
+                let max_y = grid.height as f64 - 1.0;
+                grid.cursor_x = self.turtle.x.round().clamp(0.0, max_x) as usize;
+                grid.cursor_y = self.turtle.y.round().clamp(0.0, max_y) as usize;
+            }
+        }
+    }
+}
+
+fn flatten(commands: &[Command], steps: &mut Vec<Step>) {
+    for command in commands {
+        match command {
+            Command::Forward(distance) => steps.push(Step::Forward(*distance)),
+            Command::Turn(angle) => steps.push(Step::Turn(*angle)),
+            Command::Pen(down) => steps.push(Step::Pen(*down)),
+            Command::Color(color) => steps.push(Step::Color(*color)),
+            Command::Repeat(count, body) => {
+                for _ in 0..*count {
+                    flatten(body, steps);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> String {
+        parse(source).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_programs() {
+        assert!(parse("repeat 4 [fd 5 rt 90] ; a square\npu bk 2 color 3 pd").is_ok());
+        assert!(parse("").is_ok());
+    }
+
+    #[test]
+    fn reports_where_mistakes_are() {
+        assert_eq!(
+            error("forward"),
+            "1:8: `forward` needs a number, found the end of the program"
+        );
+        assert_eq!(error("fd 1\njump 3"), "2:1: unknown command `jump`");
+        assert_eq!(
+            error("fd 1\n  repeat 2 [fd 1"),
+            "2:12: this `[` is never closed by a `]`"
+        );
+        assert_eq!(
+            error("repeat 2 fd 1"),
+            "1:10: expected `[` after `repeat 2`, found `fd`"
+        );
+        assert_eq!(error("color 12"), "1:7: colors go from 1 to 9, not `12`");
+        assert_eq!(error("fd 1 ]"), "1:6: expected a command, found `]`");
+        assert!(error("repeat 1000 [repeat 1000 [fd 1]]").contains("more than 100000 steps"));
+    }
+
+    #[test]
+    fn walks_and_paints_one_cell_at_a_time() {
+        let mut grid = Grid::new(5, 5);
+        let program = parse("fd 2 rt 90 pu fd 2 pd fd 1").ok().unwrap();
+        let mut run = Run::new(&program, Turtle::new(0, 4));
+        assert!(run.advance(&mut grid));
+        assert_eq!(grid.cells()[4][0], grid.color);
+        run.finish(&mut grid);
+        let filled: Vec<(usize, usize)> = (0..5)
+            .flat_map(|y| (0..5).map(move |x| (x, y)))
+            .filter(|&(x, y)| grid.cells()[y][x] != crate::palette::EMPTY)
+            .collect();
+        assert_eq!(filled, [(0, 2), (2, 2), (3, 2), (0, 3), (0, 4)]);
+        assert_eq!((grid.cursor_x, grid.cursor_y), (3, 2));
+    }
+}
@@ -0,0 +1,11 @@
+use ratatui::style::Color;
+
+/// Colors picked up in `move` by touching the left, right, top and bottom border.
+pub const BORDER_COLORS: [Color; 4] = [Color::Red, Color::Blue, Color::Green, Color::Yellow];
+
+/// Colors of the special objects dropped in `move` when two same-colored squares collide.
+pub const SPECIAL_COLORS: [Color; 3] = [
+    Color::Rgb(128, 0, 128),
+    Color::Cyan,
+    Color::Rgb(255, 165, 0),
+];
@@ -0,0 +1,3 @@
+//! Bits shared between the kli games.
+
+pub mod colors;